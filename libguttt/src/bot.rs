@@ -0,0 +1,17 @@
+mod flat;
+mod mcts;
+mod alphabeta;
+mod scoring;
+mod analysis;
+mod book;
+mod config;
+mod solver;
+
+pub use flat::*;
+pub use mcts::*;
+pub use alphabeta::*;
+pub use scoring::*;
+pub use analysis::*;
+pub use book::*;
+pub use config::*;
+pub use solver::*;
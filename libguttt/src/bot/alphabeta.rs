@@ -0,0 +1,262 @@
+use crate::tic_tac_toe::*;
+use super::Scoring;
+use std::rc::Rc;
+use std::any::Any;
+use std::time::{Instant,Duration};
+
+/// Score of the best possible outcome. Evaluations are kept strictly
+/// inside `-WIN..WIN`, and outcomes found sooner weigh a little more.
+pub const WIN: f64 = 1000.0;
+
+/// Guesses how good an unfinished position is for `player`, higher being
+/// better. Finished games are scored by the search itself.
+pub trait Evaluate<T, P: Player> {
+  fn evaluate(&self, game: &T, player: P) -> f64;
+}
+
+impl<T, P: Player, F: Fn(&T,P) -> f64> Evaluate<T,P> for F {
+  fn evaluate(&self, game: &T, player: P) -> f64 {
+    self(game, player)
+  }
+}
+
+/// Knows nothing about the position, so the search only sees wins and
+/// losses within its depth.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoEval;
+
+impl<T, P: Player> Evaluate<T,P> for NoEval {
+  fn evaluate(&self, _game: &T, _player: P) -> f64 {
+    0.0
+  }
+}
+
+/// Looks at the meta board of a `SuperTicTacToe`: boards won, two won
+/// boards in an open line, and where the jump leaves the player to move.
+#[derive(Copy, Clone, Debug)]
+pub struct MetaHeuristic {
+  /// Each board won by the player's team, or lost to another.
+  pub board: f64,
+  /// Extra for the centre board, which is in the most lines.
+  pub centre: f64,
+  /// Each line with all but one board won and the last still playing.
+  pub two_in_row: f64,
+  /// The player to move may go to any of several boards.
+  pub free_move: f64,
+  /// The player to move is sent to a board that would finish one of their
+  /// lines.
+  pub hot_jump: f64,
+}
+
+impl Default for MetaHeuristic {
+  fn default() -> Self {
+    MetaHeuristic{board: 10.0, centre: 3.0, two_in_row: 8.0, free_move: 6.0, hot_jump: 12.0}
+  }
+}
+
+impl MetaHeuristic {
+  fn side<P: Player>(player: P, owner: P) -> f64 {
+    if owner.is_teammate(&player) {1.0} else {-1.0}
+  }
+
+  /// Boards that would finish a line for `player`'s team.
+  fn hot_boards<T: GenericGame<P>, P: Player>(game: &SuperTicTacToe<T,P>, player: P) -> Vec<Position> {
+    let board = game.get_board();
+    let mut hot = vec!();
+    for line in board.lines() {
+      let mut open = None;
+      let mut ours = 0;
+      for &(x,y) in line.iter() {
+        let cell = board.get_xy(x,y);
+        match cell.owner() {
+          Some(owner) if owner.is_teammate(&player) => ours += 1,
+          Some(_) => {},
+          None if cell.is_open() => open = Some(Position::from_xy(x,y)),
+          None => {},
+        }
+      }
+      if let Some(pos) = open {
+        if ours+1 == line.len() {
+          hot.push(pos);
+        }
+      }
+    }
+    hot
+  }
+}
+
+impl<T: GenericGame<P>, P: Player> Evaluate<SuperTicTacToe<T,P>,P> for MetaHeuristic {
+  fn evaluate(&self, game: &SuperTicTacToe<T,P>, player: P) -> f64 {
+    let board = game.get_board();
+    let centre = Position::from_cid(4);
+    let mut score = 0.0;
+    for i in 0..9 {
+      let pos = Position::from_cid(i);
+      if let BoardStatus::Won(owner) = game.get_board_status(pos) {
+        let weight = if pos == centre {self.board+self.centre} else {self.board};
+        score += weight*Self::side(player, owner);
+      }
+    }
+    for line in board.lines() {
+      let mut owner: Option<P> = None;
+      let mut won = 0;
+      let mut open = 0;
+      let mut blocked = false;
+      for &(x,y) in line.iter() {
+        let cell = board.get_xy(x,y);
+        match cell.owner() {
+          Some(p) => {
+            if owner.map(|o| !o.is_teammate(&p)).unwrap_or(false) {
+              blocked = true;
+            }
+            owner = owner.or(Some(p));
+            won += 1;
+          },
+          None if cell.is_open() => open += 1,
+          None => blocked = true,
+        }
+      }
+      if let Some(owner) = owner {
+        if !blocked && open == 1 && won+1 == line.len() {
+          score += self.two_in_row*Self::side(player, owner);
+        }
+      }
+    }
+    let to_move = game.get_player();
+    let jump = game.get_jump();
+    if jump.len() > 1 {
+      score += self.free_move*Self::side(player, to_move);
+    }
+    let hot = Self::hot_boards(game, to_move);
+    if jump.iter().any(|pos| hot.contains(pos)) {
+      score += self.hot_jump*Self::side(player, to_move);
+    }
+    score
+  }
+}
+
+impl<P: Player + 'static> Evaluate<Box<dyn DynGame<P>>,P> for MetaHeuristic {
+  fn evaluate(&self, game: &Box<dyn DynGame<P>>, player: P) -> f64 {
+    match (**game).as_any().downcast_ref::<MixedSuperTicTacToe<P>>() {
+      Some(game) => self.evaluate(game, player),
+      None => 0.0,
+    }
+  }
+}
+
+impl<T: GenericGame<P>, P: Player> Evaluate<SuperDummyGame<T,P>,P> for MetaHeuristic where
+  MetaHeuristic: Evaluate<T,P>
+{
+  fn evaluate(&self, game: &SuperDummyGame<T,P>, player: P) -> f64 {
+    match game.get_game_ref().get_game_ref() {
+      Some(game) => self.evaluate(game, player),
+      None => 0.0,
+    }
+  }
+}
+
+/// What a search settled on.
+#[derive(Clone)]
+pub struct SearchResult {
+  pub mov: Rc<dyn Any>,
+  /// From the searching player's side.
+  pub score: f64,
+  /// Deepest iteration that finished.
+  pub depth: u32,
+}
+
+/// Depth limited alpha-beta search with iterative deepening. With more than
+/// two sides everyone not on the searching player's team is assumed to play
+/// against them, whatever `scoring.paranoid` says.
+pub struct AlphaBeta<E> {
+  pub eval: E,
+  pub max_depth: u32,
+  /// Stops deepening once this is up, keeping the last finished depth.
+  pub time: Option<Duration>,
+  /// What finished games are worth, scaled to `-WIN..WIN`.
+  pub scoring: Scoring,
+}
+
+impl<E> AlphaBeta<E> {
+  pub fn new(eval: E, max_depth: u32, time: Option<Duration>) -> Self {
+    AlphaBeta{eval, max_depth, time, scoring: Scoring::default()}
+  }
+
+  /// Searches deeper and deeper until `max_depth` or the time runs out.
+  /// `None` when there are no moves.
+  pub fn search<T: GenericGame<P> + Clone, P: Player>(&self, game: &T) -> Option<SearchResult> where E: Evaluate<T,P> {
+    let player = game.get_player();
+    let mut moves = game.get_valid_moves();
+    if moves.len() == 0 {
+      return None;
+    }
+    let deadline = self.time.map(|t| Instant::now()+t);
+    let mut best: Option<SearchResult> = None;
+    for depth in 1..=self.max_depth.max(1) {
+      let mut alpha = -WIN*2.0;
+      let mut found: Option<(usize,f64)> = None;
+      for (i,m) in moves.iter().enumerate() {
+        let mut child = game.clone();
+        let result = child.mov(Rc::clone(m)).result;
+        let score = match self.value(&child, result, player, depth-1, 1, alpha, WIN*2.0, deadline) {
+          Some(score) => score,
+          // out of time before even one depth is done: any move beats none
+          None => return best.or_else(|| Some(SearchResult{mov: Rc::clone(&moves[0]), score: 0.0, depth: 0})),
+        };
+        if found.map(|(_,s)| score > s).unwrap_or(true) {
+          found = Some((i,score));
+        }
+        if score > alpha {
+          alpha = score;
+        }
+      }
+      let (i,score) = found.unwrap();
+      // the best move goes first next time, so cutoffs come early
+      let m = moves.remove(i);
+      moves.insert(0, Rc::clone(&m));
+      best = Some(SearchResult{mov: m, score, depth});
+      // a forced win or loss won't change with more depth
+      if score.abs() > WIN/2.0 {
+        break;
+      }
+    }
+    best
+  }
+
+  /// Value of `game` for `player`, `None` if the deadline passed.
+  fn value<T: GenericGame<P> + Clone, P: Player>(&self, game: &T, result: GameResult<P>, player: P, depth: u32, ply: u32, mut alpha: f64, mut beta: f64, deadline: Option<Instant>) -> Option<f64> where E: Evaluate<T,P> {
+    if deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+      return None;
+    }
+    if let GameResult::Incomplete = result {} else {
+      let reward = self.scoring.reward(&result, player);
+      return Some((2.0*reward-1.0)*(WIN-ply as f64));
+    }
+    if depth == 0 {
+      let score = self.eval.evaluate(game, player);
+      return Some(score.max(-WIN/2.0).min(WIN/2.0));
+    }
+    let moves = game.get_valid_moves();
+    if moves.len() == 0 {
+      return self.value(game, GameResult::Draw, player, depth, ply, alpha, beta, deadline);
+    }
+    let maximizing = game.get_player().is_teammate(&player);
+    let mut best = if maximizing {-WIN*2.0} else {WIN*2.0};
+    for m in moves.iter() {
+      let mut child = game.clone();
+      let result = child.mov(Rc::clone(m)).result;
+      let score = self.value(&child, result, player, depth-1, ply+1, alpha, beta, deadline)?;
+      if maximizing {
+        best = best.max(score);
+        alpha = alpha.max(score);
+      } else {
+        best = best.min(score);
+        beta = beta.min(score);
+      }
+      if alpha >= beta {
+        break;
+      }
+    }
+    Some(best)
+  }
+}
@@ -0,0 +1,94 @@
+use crate::tic_tac_toe::*;
+use std::rc::Rc;
+use std::any::Any;
+
+/// Adds `n` losses to `team` into `losses`.
+pub(crate) fn count_loss(losses: &mut Vec<(String,u64)>, team: String, n: u64) {
+  match losses.iter_mut().find(|(t,_)| *t == team) {
+    Some((_,count)) => *count += n,
+    None => losses.push((team,n)),
+  }
+}
+
+/// What a search found out about one move, from the side of the player
+/// making it.
+#[derive(Clone)]
+pub struct MoveStats {
+  pub mov: Rc<dyn Any>,
+  /// Playouts that started with this move.
+  pub visits: u64,
+  pub wins: u64,
+  pub draws: u64,
+  pub losses: u64,
+  /// The same losses split by the team that won, by `Player::get_team`.
+  pub losses_to: Vec<(String,u64)>,
+  /// Average reward by the bot's scoring, 0 to 1.
+  pub score: f64,
+}
+
+impl MoveStats {
+  pub fn new(mov: Rc<dyn Any>) -> Self {
+    MoveStats{mov, visits: 0, wins: 0, draws: 0, losses: 0, losses_to: vec!(), score: 0.0}
+  }
+
+  /// Counts one playout that ended in `result` and was worth `reward`.
+  pub fn add<P: Player>(&mut self, result: &GameResult<P>, mover: P, reward: f64) {
+    match result {
+      GameResult::Won(winner) if winner.is_teammate(&mover) => self.wins += 1,
+      GameResult::Won(winner) => {
+        self.losses += 1;
+        count_loss(&mut self.losses_to, winner.get_team(), 1);
+      },
+      _ => self.draws += 1,
+    }
+    self.score = (self.score*(self.visits as f64)+reward)/((self.visits+1) as f64);
+    self.visits += 1;
+  }
+
+  fn rate(&self, n: u64) -> f64 {
+    if self.visits == 0 {0.0} else {n as f64/self.visits as f64}
+  }
+  pub fn win_rate(&self) -> f64 {
+    self.rate(self.wins)
+  }
+  pub fn draw_rate(&self) -> f64 {
+    self.rate(self.draws)
+  }
+  pub fn loss_rate(&self) -> f64 {
+    self.rate(self.losses)
+  }
+  /// How often the move lost to `team`.
+  pub fn loss_rate_to(&self, team: &str) -> f64 {
+    self.rate(self.losses_to.iter().find(|(t,_)| t == team).map(|(_,n)| *n).unwrap_or(0))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// X, O and C all play alone.
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O, C}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::C, P::C => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+  }
+
+  #[test]
+  fn losses_by_winner() {
+    let mut stats = MoveStats::new(Rc::new(true));
+    for result in vec![GameResult::Won(P::X), GameResult::Won(P::O), GameResult::Won(P::O), GameResult::Won(P::C), GameResult::Draw] {
+      stats.add(&result, P::X, 0.0);
+    }
+    assert_eq!((stats.wins, stats.draws, stats.losses), (1, 1, 3));
+    assert_eq!(stats.losses_to, vec![(String::from("O"), 2), (String::from("C"), 1)]);
+    assert_eq!(stats.loss_rate_to("O"), 0.4);
+    assert_eq!(stats.loss_rate_to("X"), 0.0);
+  }
+}
@@ -0,0 +1,156 @@
+use crate::tic_tac_toe::*;
+use std::rc::Rc;
+use std::any::Any;
+use std::collections::HashMap;
+
+/// Moves worked out ahead of time for the first few turns. A position is
+/// the line of moves that led to it from the start, each move written as
+/// its index in `get_valid_moves`, so a book only fits the game and the
+/// players it was made for. Written out it looks like
+///
+/// ```text
+/// game S(T)
+/// players X O C
+/// teams C+X
+/// : 40
+/// 40 : 4
+/// 40 3 : 7
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OpeningBook {
+  /// Game spec the book was made for.
+  pub game: String,
+  /// Names of the players it was made for, in turn order.
+  pub players: Vec<String>,
+  /// Teams among them, each written as its members' names joined with `+`.
+  pub teams: Vec<String>,
+  moves: HashMap<Vec<usize>,usize>,
+}
+
+impl OpeningBook {
+  pub fn new(game: String, players: Vec<String>, teams: Vec<String>) -> Self {
+    OpeningBook{game, players, teams, moves: HashMap::new()}
+  }
+
+  pub fn len(&self) -> usize {
+    self.moves.len()
+  }
+
+  pub fn insert(&mut self, line: Vec<usize>, mov: usize) {
+    self.moves.insert(line, mov);
+  }
+
+  /// Index of the move to play after `line`.
+  pub fn lookup(&self, line: &[usize]) -> Option<usize> {
+    self.moves.get(line).cloned()
+  }
+
+  pub fn parse(text: &str) -> Result<OpeningBook,String> {
+    let mut book = OpeningBook::default();
+    let index = |w: &str, n: usize| w.parse::<usize>().map_err(|_| format!("book line {}: '{}' is not a move", n+1, w));
+    for (n,line) in text.lines().enumerate() {
+      let line = line.splitn(2, '#').next().unwrap_or("").trim();
+      if line.len() == 0 {
+        continue;
+      }
+      if line.starts_with("game ") {
+        book.game = String::from(line[5..].trim());
+        continue;
+      }
+      if line.starts_with("players ") {
+        book.players = line[8..].split_whitespace().map(String::from).collect();
+        continue;
+      }
+      if line.starts_with("teams ") {
+        book.teams = line[6..].split_whitespace().map(String::from).collect();
+        continue;
+      }
+      let mut halves = line.splitn(2, ':');
+      let before = halves.next().unwrap_or("");
+      let after = halves.next().ok_or_else(|| format!("book line {}: expected MOVES : MOVE", n+1))?;
+      let mut moves = vec!();
+      for w in before.split_whitespace() {
+        moves.push(index(w, n)?);
+      }
+      book.insert(moves, index(after.trim(), n)?);
+    }
+    Ok(book)
+  }
+
+  /// Fills the book `depth` moves deep from `game`, asking `choose` for the
+  /// move in each position. Only positions where some player has kept to
+  /// the book so far are looked at, so whoever follows it is covered
+  /// against anything the others play. `progress` hears the size of the
+  /// book as it grows.
+  pub fn generate<T, P, C, F>(&mut self, game: &T, depth: usize, mut choose: C, mut progress: F) where
+    T: GenericGame<P> + Clone,
+    P: Player,
+    C: FnMut(&T) -> Option<Rc<dyn Any>>,
+    F: FnMut(usize)
+  {
+    let mut players: Vec<P> = vec!(game.get_player());
+    loop {
+      let next = players[players.len()-1].next_player();
+      if players.iter().any(|p| p.get_uuid() == next.get_uuid()) {
+        break;
+      }
+      players.push(next);
+    }
+    let uuids: Vec<String> = players.iter().map(|p| p.get_uuid()).collect();
+    if depth == 0 {
+      return;
+    }
+    let mut stack: Vec<(T,Vec<usize>,Vec<String>)> = vec!((game.clone(), vec!(), uuids));
+    while let Some((game,line,followers)) = stack.pop() {
+      let moves = game.get_valid_moves();
+      let best = match self.lookup(&line) {
+        Some(i) => Some(i),
+        None => choose(&game).and_then(|m| moves.iter().position(|o| game.is_same_move(o, &m))),
+      };
+      let best = match best {
+        Some(i) => i,
+        None => continue,
+      };
+      self.insert(line.clone(), best);
+      progress(self.len());
+      let mover = game.get_player().get_uuid();
+      if line.len()+1 >= depth {
+        continue;
+      }
+      for (i,m) in moves.iter().enumerate() {
+        // leaving the book drops the mover from those who follow it
+        let followers: Vec<String> = if i == best {
+          followers.clone()
+        } else {
+          followers.iter().filter(|f| **f != mover).cloned().collect()
+        };
+        if followers.len() == 0 {
+          continue;
+        }
+        let mut next = game.clone();
+        if let GameResult::Incomplete = next.mov(Rc::clone(m)).result {
+          let mut line = line.clone();
+          line.push(i);
+          stack.push((next, line, followers));
+        }
+      }
+    }
+  }
+}
+
+impl std::fmt::Display for OpeningBook {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    writeln!(f, "game {}", self.game)?;
+    writeln!(f, "players {}", self.players.join(" "))?;
+    if self.teams.len() > 0 {
+      writeln!(f, "teams {}", self.teams.join(" "))?;
+    }
+    let mut lines: Vec<(&Vec<usize>,&usize)> = self.moves.iter().collect();
+    lines.sort();
+    for (line,m) in lines.into_iter() {
+      let words: Vec<String> = line.iter().map(|i| format!("{} ", i)).collect();
+      writeln!(f, "{}: {}", words.concat(), m)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,364 @@
+use crate::tic_tac_toe::*;
+use super::*;
+use std::rc::Rc;
+use std::any::Any;
+use std::time::{Duration,Instant};
+use std::sync::Arc;
+use rand::seq::SliceRandom;
+
+/// How a bot picks its moves.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Algorithm {
+  /// Any valid move.
+  Random,
+  /// Flat Monte Carlo, `best_move`.
+  Flat,
+  /// Tree search, `Mcts`.
+  Mcts,
+  /// Minimax with the meta board heuristic, `AlphaBeta`.
+  AlphaBeta,
+}
+
+impl Algorithm {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Algorithm::Random => "random",
+      Algorithm::Flat => "flat",
+      Algorithm::Mcts => "mcts",
+      Algorithm::AlphaBeta => "alphabeta",
+    }
+  }
+
+  pub fn parse(s: &str) -> Option<Algorithm> {
+    match s {
+      "random" => Some(Algorithm::Random),
+      "flat" => Some(Algorithm::Flat),
+      "mcts" => Some(Algorithm::Mcts),
+      "alphabeta" | "ab" => Some(Algorithm::AlphaBeta),
+      _ => None,
+    }
+  }
+}
+
+/// Everything that decides how strong a bot plays.
+#[derive(Clone, Debug)]
+pub struct BotConfig {
+  pub algorithm: Algorithm,
+  /// Thinking time per move. A running clock can cut it shorter.
+  pub time: Duration,
+  /// Stop after this many playouts instead of when the time is up.
+  pub playouts: Option<u64>,
+  /// UCT exploration constant for `Mcts`.
+  pub exploration: f64,
+  /// Deepest `AlphaBeta` goes.
+  pub depth: u32,
+  pub scoring: Scoring,
+  /// Play a quantum game with `QuantumSolver` when it can finish in time,
+  /// and take quantum boards that one move wins inside bigger games.
+  pub solve: bool,
+}
+
+pub const PRESETS: [&str; 5] = ["random", "easy", "medium", "hard", "max"];
+
+/// Pondering stops once the tree holds this many playouts, to keep it from
+/// eating all the memory while someone takes their time.
+const PONDER_PLAYOUTS: u64 = 500_000;
+
+impl Default for BotConfig {
+  fn default() -> Self {
+    BotConfig::preset("medium").unwrap()
+  }
+}
+
+impl BotConfig {
+  /// One of the named difficulties in `PRESETS`.
+  pub fn preset(name: &str) -> Option<BotConfig> {
+    let base = BotConfig{
+      algorithm: Algorithm::Mcts,
+      time: Duration::from_millis(300),
+      playouts: None,
+      exploration: 2f64.sqrt(),
+      depth: 64,
+      scoring: Scoring::default(),
+      solve: true,
+    };
+    Some(match name {
+      "random" => BotConfig{algorithm: Algorithm::Random, solve: false, ..base},
+      "easy" => BotConfig{algorithm: Algorithm::Flat, playouts: Some(8), solve: false, ..base},
+      "medium" => base,
+      "hard" => BotConfig{time: Duration::from_millis(1500), ..base},
+      "max" => BotConfig{time: Duration::from_millis(5000), ..base},
+      _ => return None,
+    })
+  }
+
+  /// A preset or algorithm name, then any `,KEY=VALUE` changes, like
+  /// `hard,time=800` or `alphabeta,depth=6`. Keys are `time` in
+  /// milliseconds, `playouts`, `c` for exploration, `depth` and `algo`,
+  /// and for the scoring `win`, `draw`, `loss`, `loss.NAME` for losing to
+  /// NAME's team, and `objective` as `score`, `winrate` or `paranoid`.
+  /// `solve=false` keeps the bot from solving quantum boards.
+  /// `find_team` turns player names into teams.
+  pub fn parse<F: Fn(&str) -> Option<String>>(s: &str, find_team: F) -> Result<BotConfig,String> {
+    let mut parts = s.split(',');
+    let base = parts.next().unwrap_or("").trim();
+    let mut config = match (BotConfig::preset(base), Algorithm::parse(base)) {
+      (Some(config), _) => config,
+      (None, Some(algorithm)) => BotConfig{algorithm, ..BotConfig::default()},
+      (None, None) => return Err(format!(
+        "'{}' is not a bot, try one of {} or random, flat, mcts, alphabeta",
+        base, PRESETS.join(", ")
+      )),
+    };
+    // goes by the final draw and loss values, whatever order they came in
+    let mut winrate = false;
+    for part in parts {
+      let mut kv = part.splitn(2, '=');
+      let key = kv.next().unwrap_or("").trim();
+      let value = kv.next().ok_or_else(|| format!("bot setting '{}' needs a value", part))?.trim();
+      let bad = || format!("bad value '{}' for bot setting {}", value, key);
+      match key {
+        "time" => config.time = Duration::from_millis(value.parse().map_err(|_| bad())?),
+        "playouts" => config.playouts = Some(value.parse().map_err(|_| bad())?),
+        "c" | "exploration" => config.exploration = value.parse().map_err(|_| bad())?,
+        "depth" => config.depth = value.parse().map_err(|_| bad())?,
+        "solve" => config.solve = value.parse().map_err(|_| bad())?,
+        "algo" | "algorithm" => config.algorithm = Algorithm::parse(value).ok_or_else(bad)?,
+        "win" => config.scoring.win = value.parse().map_err(|_| bad())?,
+        "draw" => config.scoring.draw = value.parse().map_err(|_| bad())?,
+        "loss" => config.scoring.loss = value.parse().map_err(|_| bad())?,
+        "objective" => match value {
+          "score" => {
+            config.scoring.paranoid = false;
+            winrate = false;
+          },
+          "winrate" => winrate = true,
+          "paranoid" => config.scoring.paranoid = true,
+          _ => return Err(bad()),
+        },
+        _ if key.starts_with("loss.") => {
+          let name = &key[5..];
+          let team = find_team(name).ok_or_else(|| format!("bot setting {}: unknown player '{}'", key, name))?;
+          let v: f64 = value.parse().map_err(|_| bad())?;
+          config.scoring.losses.retain(|(t,_)| *t != team);
+          config.scoring.losses.push((team, v));
+        },
+        _ => return Err(format!("unknown bot setting '{}'", key)),
+      }
+    }
+    if winrate {
+      config.scoring.draw = config.scoring.loss;
+    }
+    Ok(config)
+  }
+}
+
+/// A bot playing one seat, keeping whatever it remembers between moves.
+/// Tell it about every move played with `advance`, and `reset` it when a
+/// new game starts.
+pub struct Bot<T: GenericGame<P> + Clone, P: Player> {
+  pub config: BotConfig,
+  pub threads: usize,
+  /// Played from instead of searching while the game is still in it.
+  pub book: Option<Arc<OpeningBook>>,
+  tree: Option<Mcts<T,P>>,
+  /// Moves since the start as book indices, until one isn't known.
+  line: Option<Vec<usize>>,
+  /// What the last search found, for bots that keep statistics.
+  last: Vec<MoveStats>,
+  solver: QuantumSolver,
+}
+
+impl<T, P> Bot<T,P> where
+  T: GenericGame<P> + Clone + Send,
+  P: Player + Send,
+  MetaHeuristic: Evaluate<T,P>,
+  QuantumSolver: Solve<T,P>
+{
+  pub fn new(config: BotConfig, threads: usize) -> Self {
+    Bot{config, threads, book: None, tree: None, line: Some(vec!()), last: vec!(), solver: QuantumSolver::new()}
+  }
+
+  fn book_move(&self, game: &T) -> Option<Rc<dyn Any>> {
+    let i = self.book.as_ref()?.lookup(self.line.as_ref()?)?;
+    game.get_valid_moves().get(i).map(Rc::clone)
+  }
+
+  /// Picks a move for whoever is to move in `game`, thinking for at most
+  /// `limit` on top of the configured time.
+  pub fn choose<R: rand::Rng>(&mut self, game: &T, limit: Option<Duration>, rng: &mut R) -> Option<Rc<dyn Any>> {
+    self.last = vec!();
+    if let Some(m) = self.book_move(game) {
+      return Some(m);
+    }
+    let started = Instant::now();
+    let time = limit.map(|l| l.min(self.config.time)).unwrap_or(self.config.time);
+    if self.config.solve {
+      // a quarter of the time, the search still has the rest if it fails
+      let budget = match self.config.playouts {
+        Some(n) => Budget::Playouts(n),
+        None => Budget::Time(time/4),
+      };
+      if let Some(solution) = self.solver.solve_in(game, budget) {
+        return Some(solution.mov);
+      }
+    }
+    // whatever the solver took comes off the search
+    let time = time.saturating_sub(started.elapsed());
+    match self.config.algorithm {
+      Algorithm::Random => game.get_valid_moves().choose(rng).map(Rc::clone),
+      Algorithm::Flat => {
+        let mode = match self.config.playouts {
+          Some(n) => BotMode::Count(n, None),
+          None => BotMode::Time(time.as_millis() as u64, None),
+        };
+        self.last = flat_analysis(game.clone(), game.get_player(), rng, mode, &self.config.scoring);
+        let mut best: Option<&MoveStats> = None;
+        for m in self.last.iter() {
+          if best.map(|b| m.score > b.score).unwrap_or(true) {
+            best = Some(m);
+          }
+        }
+        best.map(|b| Rc::clone(&b.mov))
+      },
+      Algorithm::Mcts => {
+        let mut mcts = self.tree.take().unwrap_or_else(|| Mcts::new(game.clone()));
+        mcts.exploration = self.config.exploration;
+        mcts.threads = self.threads;
+        mcts.scoring = self.config.scoring.clone();
+        mcts.side = Some(game.get_player());
+        let budget = match self.config.playouts {
+          Some(n) => Budget::Playouts(n),
+          None => Budget::Time(time),
+        };
+        mcts.search(budget, rng);
+        let m = mcts.best_move();
+        self.last = mcts.analysis();
+        self.tree = Some(mcts);
+        m
+      },
+      Algorithm::AlphaBeta => {
+        let mut ab = AlphaBeta::new(MetaHeuristic::default(), self.config.depth, Some(time));
+        ab.scoring = self.config.scoring.clone();
+        ab.search(game).map(|r| r.mov)
+      },
+    }
+  }
+
+  /// Thinks for up to `time` about `game`, where someone other than `me` is
+  /// to move, so the tree is already grown for whatever they play. Only
+  /// `Mcts` bots have a tree to grow; returns false when there's nothing
+  /// more to do.
+  pub fn ponder<R: rand::Rng>(&mut self, game: &T, me: P, time: Duration, rng: &mut R) -> bool {
+    if self.config.algorithm != Algorithm::Mcts {
+      return false;
+    }
+    let mut mcts = self.tree.take().unwrap_or_else(|| Mcts::new(game.clone()));
+    let more = mcts.get_playouts() < PONDER_PLAYOUTS;
+    if more {
+      mcts.exploration = self.config.exploration;
+      mcts.threads = 1;
+      mcts.scoring = self.config.scoring.clone();
+      mcts.side = Some(me);
+      mcts.search(Budget::Time(time), rng);
+    }
+    self.tree = Some(mcts);
+    more
+  }
+
+  /// Follows a move played in the game, `before` being the game just
+  /// before it.
+  pub fn advance(&mut self, before: &T, m: &Rc<dyn Any>) {
+    if let Some(tree) = &mut self.tree {
+      tree.advance(m);
+    }
+    if let Some(line) = &mut self.line {
+      match before.get_valid_moves().iter().position(|o| before.is_same_move(o, m)) {
+        Some(i) if self.book.is_some() => line.push(i),
+        _ => self.line = None,
+      }
+    }
+  }
+
+  /// Statistics from the last search, for each move it looked at. Empty
+  /// when the move came from the book or the algorithm keeps none.
+  pub fn analysis(&self) -> &[MoveStats] {
+    &self.last
+  }
+
+  /// Forgets the last game, solved positions included, so the next one
+  /// plays out the same from the same seed.
+  pub fn reset(&mut self) {
+    self.tree = None;
+    self.solver = QuantumSolver::new();
+    self.line = Some(vec!());
+  }
+
+  /// Stops looking in the book for this game, like when it didn't start
+  /// from the usual position.
+  pub fn leave_book(&mut self) {
+    self.line = None;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use rand::SeedableRng;
+  use rand::rngs::StdRng;
+
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+  }
+
+  /// The first moves of an ultimate game between two bots seeded with `seed`.
+  fn bot_game(spec: &str, threads: usize, seed: u64) -> Vec<String> {
+    let config = BotConfig::parse(spec, |_: &str| None).unwrap();
+    let mut bots = vec![Bot::new(config.clone(), threads), Bot::new(config, threads)];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = SuperTicTacToe::new(P::X, |_, p| TicTacToe::new(p));
+    let mut moves = vec!();
+    for turn in 0..12 {
+      let before = game.clone();
+      let m = bots[turn%2].choose(&game, None, &mut rng).unwrap();
+      moves.push(move_notation(&m));
+      for bot in bots.iter_mut() {
+        bot.advance(&before, &m);
+      }
+      if let GameResult::Incomplete = game.mov(m).result {} else {
+        break;
+      }
+    }
+    moves
+  }
+
+  #[test]
+  fn same_seed_same_game() {
+    for &threads in [1, 3].iter() {
+      let first = bot_game("mcts,playouts=50", threads, 7);
+      assert_eq!(first, bot_game("mcts,playouts=50", threads, 7));
+      assert!(first != bot_game("mcts,playouts=50", threads, 8));
+    }
+    let flat = bot_game("flat,playouts=5", 1, 7);
+    assert_eq!(flat, bot_game("flat,playouts=5", 1, 7));
+  }
+
+  #[test]
+  fn objective_goes_by_final_values() {
+    let none = |_: &str| None;
+    let before = BotConfig::parse("mcts,objective=winrate,loss=-2", none).unwrap();
+    let after = BotConfig::parse("mcts,loss=-2,objective=winrate", none).unwrap();
+    assert_eq!(before.scoring.draw, -2.0);
+    assert_eq!(after.scoring.draw, -2.0);
+    let undone = BotConfig::parse("mcts,objective=winrate,objective=score", none).unwrap();
+    assert_eq!(undone.scoring.draw, Scoring::default().draw);
+  }
+}
@@ -0,0 +1,93 @@
+use crate::tic_tac_toe::*;
+use super::{Scoring,MoveStats};
+use std::rc::Rc;
+use std::any::Any;
+use std::time::{Instant,Duration};
+use rand::seq::IteratorRandom;
+
+/// Flat Monte Carlo budget: playouts per root move, by time or count.
+/// The inner mode makes playouts pick their moves with a nested search.
+#[derive(Clone)]
+pub enum BotMode {
+  Time(u64,Option<Box<BotMode>>),
+  Count(u64,Option<Box<BotMode>>)
+}
+
+/// The move with the best average `scoring` over random playouts after it.
+pub fn best_move<T: GenericGame<P> + Clone,P: Player, R: rand::Rng>(game: T, player: P, rng: &mut R, dat: BotMode, scoring: &Scoring) -> Option<Rc<dyn Any>> {
+  let mut biggest = std::f64::MIN;
+  let mut bm: Option<Rc<dyn Any>> = None;
+  for m in flat_analysis(game,player,rng,dat,scoring).into_iter() {
+    if m.score > biggest {
+      biggest = m.score;
+      bm = Some(m.mov);
+    }
+  }
+  bm
+}
+
+/// Playout statistics for every valid move, in `get_valid_moves` order.
+pub fn flat_analysis<T: GenericGame<P> + Clone,P: Player, R: rand::Rng>(game: T, player: P, rng: &mut R, dat: BotMode, scoring: &Scoring) -> Vec<MoveStats> {
+  let vmoves = game.get_valid_moves();
+  if vmoves.len() == 0 {
+    return vec!();
+  }
+  let mut move_score = Vec::<MoveStats>::new();
+  let rmc = vmoves.len();
+  let mut maxtime: Option<Duration> = None;
+  let mut maxcount: Option<u64> = None;
+  let bot: Option<BotMode>;
+  match dat.clone() {
+    BotMode::Time(n,b) => {bot = if let Some(rb) = b {Some(*rb)} else {None};maxtime = Some(Duration::from_millis(n/(rmc as u64)));},
+    BotMode::Count(n,b) => {bot = if let Some(rb) = b {Some(*rb)} else {None};maxcount = Some(n);}
+  }
+  for m in vmoves.iter() {
+    let mut stats = MoveStats::new(Rc::clone(m));
+    let st = Instant::now();
+    let mut count: u64 = 0;
+    loop {
+      let mut gres = GameResult::<P>::Incomplete;
+      let mut game = game.clone();
+      game.mov(Rc::clone(m));
+      let mut movesn: f64 = 0.0;
+      loop {
+        let rmove: Option<Rc<dyn Any>>;
+        match bot.clone() {
+          Some(botmode) => {
+            rmove = best_move(game.clone(),player,rng,botmode,scoring);
+          },
+          None => {
+            let moves = game.get_valid_moves();
+            rmove = if let Some(rm) = moves.iter().choose(rng) {
+              Some(Rc::clone(rm))
+            } else {
+              None
+            };
+          }
+        }
+        if let Some(rm) = rmove {
+          movesn += 1.0;
+          gres = game.mov(Rc::clone(&rm)).result;
+        } else {
+          break;
+        }
+      }
+      stats.add(&gres, player, scoring.reward(&gres, player));
+      count += 1;
+      match &dat {
+        BotMode::Time(_,_) => {
+          if st.elapsed() >= maxtime.unwrap() {
+            break;
+          }
+        },
+        BotMode::Count(_,_) => {
+          if count >= maxcount.unwrap() {
+            break;
+          }
+        }
+      }
+    }
+    move_score.push(stats)
+  }
+  move_score
+}
@@ -0,0 +1,363 @@
+use crate::tic_tac_toe::*;
+use super::{Scoring,MoveStats};
+use super::analysis::count_loss;
+use std::rc::Rc;
+use std::any::Any;
+use std::time::{Instant,Duration};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::thread;
+
+/// How much thinking a search may do.
+#[derive(Copy, Clone, Debug)]
+pub enum Budget {
+  Time(Duration),
+  Playouts(u64),
+}
+
+struct Node<P: Player> {
+  mov: Option<Rc<dyn Any>>,
+  /// Who made `mov`. Scores are from their side.
+  mover: Option<P>,
+  children: Vec<Node<P>>,
+  untried: Option<Vec<Rc<dyn Any>>>,
+  result: GameResult<P>,
+  visits: u64,
+  score: f64,
+  wins: u64,
+  draws: u64,
+  /// Losses by the team that won.
+  losses: Vec<(String,u64)>,
+}
+
+/// A root move's numbers, sent back from a search thread.
+struct RootStat {
+  /// Position in `get_valid_moves`.
+  index: usize,
+  visits: u64,
+  score: f64,
+  wins: u64,
+  draws: u64,
+  losses: Vec<(String,u64)>,
+}
+
+impl<P: Player> Node<P> {
+  fn new(mov: Option<Rc<dyn Any>>, mover: Option<P>, result: GameResult<P>) -> Self {
+    Node{mov, mover, children: vec!(), untried: None, result, visits: 0, score: 0.0, wins: 0, draws: 0, losses: vec!()}
+  }
+
+  fn is_terminal(&self) -> bool {
+    if let GameResult::Incomplete = self.result {false} else {true}
+  }
+
+  /// Counts a playout through this node that ended in `result`.
+  fn record(&mut self, result: &GameResult<P>, scoring: &Scoring, searcher: P) {
+    self.visits += 1;
+    if let Some(mover) = self.mover {
+      self.score += scoring.reward_for(result, mover, searcher);
+      match result {
+        GameResult::Won(winner) if winner.is_teammate(&mover) => self.wins += 1,
+        GameResult::Won(winner) => count_loss(&mut self.losses, winner.get_team(), 1),
+        _ => self.draws += 1,
+      }
+    }
+  }
+
+  fn uct(&self, parent_visits: u64, exploration: f64) -> f64 {
+    if self.visits == 0 {
+      return std::f64::INFINITY;
+    }
+    let n = self.visits as f64;
+    self.score/n + exploration*((parent_visits as f64).ln()/n).sqrt()
+  }
+}
+
+/// Monte Carlo tree search with UCT selection. Keep it around between
+/// moves and `advance` it, so the part of the tree that's still relevant
+/// gets reused.
+///
+/// With more than one thread, every extra thread searches a tree of its
+/// own from the same root. Their counts for the root moves are kept next
+/// to this tree's until it advances and go into `best_move` and
+/// `analysis`, but not into the tree, since there's nothing under them.
+pub struct Mcts<T: GenericGame<P> + Clone, P: Player> {
+  game: T,
+  root: Node<P>,
+  pub exploration: f64,
+  pub threads: usize,
+  /// Rewards are from the side of whoever is to move at the root.
+  pub scoring: Scoring,
+  /// Whose side a paranoid `scoring` takes, if not whoever is to move at
+  /// the root, like when the tree grows on someone else's turn.
+  pub side: Option<P>,
+  /// Root move counts from the other threads.
+  shared: Vec<RootStat>,
+}
+
+impl<T: GenericGame<P> + Clone, P: Player> Mcts<T,P> {
+  pub fn new(game: T) -> Self {
+    Mcts{game, root: Node::new(None, None, GameResult::Incomplete), exploration: 2f64.sqrt(), threads: 1, scoring: Scoring::default(), side: None, shared: vec!()}
+  }
+
+  pub fn get_game(&self) -> &T {
+    &self.game
+  }
+
+  /// Playouts that went through the current root.
+  pub fn get_playouts(&self) -> u64 {
+    self.root.visits
+  }
+
+  /// Moves the root along `m`, keeping its subtree if it was explored.
+  pub fn advance(&mut self, m: &Rc<dyn Any>) {
+    let children = std::mem::replace(&mut self.root.children, vec!());
+    let mut next = None;
+    for child in children.into_iter() {
+      if let Some(cm) = &child.mov {
+        if self.game.is_same_move(cm, m) {
+          next = Some(child);
+          break;
+        }
+      }
+    }
+    let mdata = self.game.mov(Rc::clone(m));
+    self.shared = vec!();
+    self.root = match next {
+      Some(child) => child,
+      None => Node::new(None, None, mdata.result),
+    };
+    self.root.mov = None;
+  }
+
+  /// Searches on `threads` threads. A playout budget is shared between
+  /// them, with what doesn't divide evenly and at least one playout left
+  /// to this thread; a time budget applies to each.
+  pub fn search<R: rand::Rng>(&mut self, budget: Budget, rng: &mut R) where T: Send, P: Send {
+    let threads = self.threads.max(1);
+    if threads == 1 || self.root.is_terminal() {
+      self.search_here(budget, rng);
+      return;
+    }
+    let (share,own) = match budget {
+      Budget::Playouts(n) => {
+        let each = n/(threads as u64);
+        (Budget::Playouts(each), Budget::Playouts((each+n%(threads as u64)).max(1)))
+      },
+      b => (b,b),
+    };
+    let exploration = self.exploration;
+    let side = self.side;
+    let workers: Vec<(T,Scoring,u64)> = (1..threads).map(|_| (self.game.clone(), self.scoring.clone(), rng.gen())).collect();
+    let stats: Vec<Vec<RootStat>> = thread::scope(|s| {
+      let handles: Vec<_> = workers.into_iter().map(|(game,scoring,seed)| {
+        s.spawn(move || {
+          let mut rng = StdRng::seed_from_u64(seed);
+          let mut worker = Mcts::<T,P>::new(game);
+          worker.exploration = exploration;
+          worker.scoring = scoring;
+          worker.side = side;
+          worker.search_here(share, &mut rng);
+          worker.root_stats()
+        })
+      }).collect();
+      self.search_here(own, rng);
+      handles.into_iter().map(|h| h.join().expect("search thread panicked")).collect()
+    });
+    for s in stats.into_iter() {
+      self.merge(s);
+    }
+  }
+
+  fn search_here<R: rand::Rng>(&mut self, budget: Budget, rng: &mut R) {
+    let start = Instant::now();
+    let mut count = 0;
+    loop {
+      match budget {
+        // one playout at least, so there's a move even with no time left
+        Budget::Time(t) => if count > 0 && start.elapsed() >= t {break},
+        Budget::Playouts(n) => if count >= n {break},
+      }
+      if !self.iterate(rng) {
+        break;
+      }
+      count += 1;
+    }
+  }
+
+  /// Numbers for every explored root move, by its index in
+  /// `get_valid_moves`. Moves can't leave their thread, indices can.
+  fn root_stats(&self) -> Vec<RootStat> {
+    let moves = self.game.get_valid_moves();
+    let mut stats = vec!();
+    for child in self.root.children.iter() {
+      let cm = child.mov.as_ref().unwrap();
+      if let Some(i) = moves.iter().position(|m| self.game.is_same_move(cm, m)) {
+        stats.push(RootStat{index: i, visits: child.visits, score: child.score, wins: child.wins, draws: child.draws, losses: child.losses.clone()});
+      }
+    }
+    stats
+  }
+
+  /// Adds another search's `root_stats` to the counts kept beside the
+  /// tree.
+  fn merge(&mut self, stats: Vec<RootStat>) {
+    for stat in stats.into_iter() {
+      match self.shared.iter_mut().find(|s| s.index == stat.index) {
+        Some(shared) => {
+          shared.visits += stat.visits;
+          shared.score += stat.score;
+          shared.wins += stat.wins;
+          shared.draws += stat.draws;
+          for (team,n) in stat.losses.into_iter() {
+            count_loss(&mut shared.losses, team, n);
+          }
+        },
+        None => self.shared.push(stat),
+      }
+    }
+  }
+
+  /// One round of selection, expansion, playout and backpropagation.
+  /// Returns false when there's nothing left to search.
+  pub fn iterate<R: rand::Rng>(&mut self, rng: &mut R) -> bool {
+    if self.root.is_terminal() {
+      return false;
+    }
+    let mut game = self.game.clone();
+    let searcher = self.side.unwrap_or(game.get_player());
+    Self::descend(&mut self.root, &mut game, self.exploration, &self.scoring, searcher, rng);
+    true
+  }
+
+  fn descend<R: rand::Rng>(node: &mut Node<P>, game: &mut T, exploration: f64, scoring: &Scoring, searcher: P, rng: &mut R) -> GameResult<P> {
+    let result = if node.is_terminal() {
+      node.result
+    } else {
+      if node.untried.is_none() {
+        let mut moves = game.get_valid_moves();
+        moves.shuffle(rng);
+        node.untried = Some(moves);
+      }
+      if let Some(m) = node.untried.as_mut().unwrap().pop() {
+        let mover = game.get_player();
+        let mdata = game.mov(Rc::clone(&m));
+        let mut child = Node::new(Some(m), Some(mover), mdata.result);
+        let result = if child.is_terminal() {
+          child.result
+        } else {
+          Self::playout(game, rng)
+        };
+        child.record(&result, scoring, searcher);
+        node.children.push(child);
+        result
+      } else if node.children.len() == 0 {
+        GameResult::Draw
+      } else {
+        let visits = node.visits;
+        let mut best = 0;
+        let mut best_uct = std::f64::MIN;
+        for (i,child) in node.children.iter().enumerate() {
+          let uct = child.uct(visits, exploration);
+          if uct > best_uct {
+            best = i;
+            best_uct = uct;
+          }
+        }
+        let child = &mut node.children[best];
+        game.mov(Rc::clone(child.mov.as_ref().unwrap()));
+        Self::descend(child, game, exploration, scoring, searcher, rng)
+      }
+    };
+    node.record(&result, scoring, searcher);
+    result
+  }
+
+  /// Random moves until the game ends.
+  fn playout<R: rand::Rng>(game: &mut T, rng: &mut R) -> GameResult<P> {
+    loop {
+      let moves = game.get_valid_moves();
+      match moves.choose(rng) {
+        Some(m) => {
+          let result = game.mov(Rc::clone(m)).result;
+          if let GameResult::Incomplete = result {} else {
+            return result;
+          }
+        },
+        None => return GameResult::Draw,
+      }
+    }
+  }
+
+  /// The most visited move at the root.
+  pub fn best_move(&self) -> Option<Rc<dyn Any>> {
+    self.analysis().into_iter().next().filter(|s| s.visits > 0).map(|s| s.mov)
+  }
+
+  /// Every root move, most visited first, with the ones not tried yet at
+  /// the end and no visits.
+  pub fn analysis(&self) -> Vec<MoveStats> {
+    let moves = self.game.get_valid_moves();
+    let mut stats: Vec<MoveStats> = moves.iter().map(|m| MoveStats::new(Rc::clone(m))).collect();
+    let counts = self.root.children.iter().filter_map(|child| {
+      let cm = child.mov.as_ref().unwrap();
+      moves.iter().position(|m| self.game.is_same_move(cm, m))
+        .map(|i| (i, child.visits, child.score, child.wins, child.draws, &child.losses))
+    }).chain(self.shared.iter().map(|s| (s.index, s.visits, s.score, s.wins, s.draws, &s.losses)));
+    // scores are summed here and averaged below
+    for (i,visits,score,wins,draws,losses) in counts {
+      let stat = &mut stats[i];
+      stat.visits += visits;
+      stat.score += score;
+      stat.wins += wins;
+      stat.draws += draws;
+      for (team,n) in losses.iter() {
+        count_loss(&mut stat.losses_to, team.clone(), *n);
+      }
+    }
+    for stat in stats.iter_mut() {
+      stat.losses = stat.visits-stat.wins-stat.draws;
+      stat.score /= stat.visits.max(1) as f64;
+    }
+    stats.sort_by(|a,b| b.visits.cmp(&a.visits));
+    stats
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+  }
+
+  #[test]
+  fn other_threads_stay_out_of_the_tree() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut mcts = Mcts::new(SuperTicTacToe::new(P::X, |_, p| TicTacToe::new(p)));
+    mcts.threads = 4;
+    mcts.search(Budget::Playouts(400), &mut rng);
+    let total = |mcts: &Mcts<_,P>| mcts.analysis().iter().map(|s| s.visits).sum::<u64>();
+    assert_eq!(mcts.get_playouts(), 100);
+    assert_eq!(total(&mcts), 400);
+    assert_eq!(mcts.analysis().len(), 81);
+    // after a move only what this thread searched under it is left
+    let m = mcts.best_move().unwrap();
+    mcts.advance(&m);
+    let kept = mcts.get_playouts();
+    assert!(kept < 100);
+    assert_eq!(total(&mcts), kept.saturating_sub(1));
+    mcts.search(Budget::Playouts(400), &mut rng);
+    assert_eq!(mcts.get_playouts(), kept+100);
+    assert_eq!(total(&mcts), kept.saturating_sub(1)+400);
+  }
+}
@@ -0,0 +1,70 @@
+use crate::tic_tac_toe::*;
+
+/// What a bot plays for: how much each way a game can end is worth to it.
+/// A loss can be worth different amounts depending on who won, so with
+/// three players a bot can rather draw than let one of them win.
+#[derive(Clone, Debug)]
+pub struct Scoring {
+  pub win: f64,
+  pub draw: f64,
+  /// Losing to a team not in `losses`.
+  pub loss: f64,
+  /// Losing to particular teams, by `Player::get_team`.
+  pub losses: Vec<(String,f64)>,
+  /// Search as if every other team played against this one, instead of
+  /// each of them going by this scoring for themselves.
+  pub paranoid: bool,
+}
+
+/// The values bots always played by: a draw is a third of the way from a
+/// loss to a win.
+impl Default for Scoring {
+  fn default() -> Self {
+    Scoring{win: 1.0, draw: -1.0, loss: -2.0, losses: vec!(), paranoid: false}
+  }
+}
+
+impl Scoring {
+  /// Only wins count, a draw is as bad as a loss.
+  pub fn win_rate() -> Self {
+    let scoring = Scoring::default();
+    Scoring{draw: scoring.loss, ..scoring}
+  }
+
+  /// What `result` is worth to `player`. Unfinished games count as
+  /// draws.
+  pub fn value<P: Player>(&self, result: &GameResult<P>, player: P) -> f64 {
+    match result {
+      GameResult::Won(winner) if winner.is_teammate(&player) => self.win,
+      GameResult::Won(winner) => {
+        let team = winner.get_team();
+        self.losses.iter().find(|(t,_)| *t == team).map(|(_,v)| *v).unwrap_or(self.loss)
+      },
+      _ => self.draw,
+    }
+  }
+
+  /// `value` scaled so the worst outcome is 0 and the best 1.
+  pub fn reward<P: Player>(&self, result: &GameResult<P>, player: P) -> f64 {
+    let values = self.losses.iter().map(|(_,v)| *v).chain(vec![self.win, self.draw, self.loss]);
+    let (min,max) = values.fold((std::f64::MAX,std::f64::MIN), |(lo,hi),v| (lo.min(v),hi.max(v)));
+    if max > min {
+      (self.value(result, player)-min)/(max-min)
+    } else {
+      0.5
+    }
+  }
+
+  /// The reward `mover` is after when `searcher` is the one searching:
+  /// their own, or with `paranoid` the opposite of the searcher's for
+  /// everyone off the searcher's team.
+  pub fn reward_for<P: Player>(&self, result: &GameResult<P>, mover: P, searcher: P) -> f64 {
+    if !self.paranoid {
+      self.reward(result, mover)
+    } else if mover.is_teammate(&searcher) {
+      self.reward(result, searcher)
+    } else {
+      1.0-self.reward(result, searcher)
+    }
+  }
+}
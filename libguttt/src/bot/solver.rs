@@ -0,0 +1,457 @@
+use crate::tic_tac_toe::*;
+use super::Budget;
+use std::rc::Rc;
+use std::any::Any;
+use std::cmp::{min,max};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Score of a win on the move. Every ply before the end takes one off, so
+/// quicker wins and slower losses score better.
+const SOLVED: i32 = 1000;
+const INF: i32 = SOLVED+1;
+/// The cache is dropped when it grows past this many positions.
+const CACHE_LIMIT: usize = 1 << 21;
+
+/// What a position is worth with perfect play, from one side.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+  /// Won in this many plies.
+  Win(u32),
+  Draw,
+  /// Lost in this many plies.
+  Loss(u32),
+}
+
+impl Outcome {
+  fn from_score(score: i32) -> Outcome {
+    if score > 0 {
+      Outcome::Win((SOLVED-score) as u32)
+    } else if score < 0 {
+      Outcome::Loss((SOLVED+score) as u32)
+    } else {
+      Outcome::Draw
+    }
+  }
+}
+
+/// A move the solver can vouch for.
+#[derive(Clone)]
+pub struct Solution {
+  pub mov: Rc<dyn Any>,
+  /// From the side of the player making it.
+  pub outcome: Outcome,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Bound {
+  Exact,
+  Lower,
+  Upper,
+}
+
+/// Plays a `QuantumTicTacToe` board perfectly by searching it to the end,
+/// collapses included. Positions it has worked out are remembered, under
+/// all eight rotations and reflections of the board, so it gets quicker
+/// the more it's asked. With more than two sides everyone else is assumed
+/// to play against the player to move.
+#[derive(Clone, Debug, Default)]
+pub struct QuantumSolver {
+  cache: HashMap<Vec<u8>,(i32,Bound)>,
+  /// Player ids, so marks fit in a byte.
+  players: Vec<String>,
+  deadline: Option<Instant>,
+  max_nodes: Option<u64>,
+  nodes: u64,
+}
+
+/// Where the cell `cid` goes under symmetry `t` of the board: bit 0 swaps
+/// the axes, bits 1 and 2 mirror them.
+fn transform(t: u8, cid: u8) -> u8 {
+  let (mut x,mut y) = (cid%3,cid/3);
+  if t&1 != 0 {
+    std::mem::swap(&mut x,&mut y);
+  }
+  if t&2 != 0 {
+    x = 2-x;
+  }
+  if t&4 != 0 {
+    y = 2-y;
+  }
+  y*3+x
+}
+
+/// Takes a ply off a score on its way up the tree.
+fn shrink(score: i32) -> i32 {
+  score-score.signum()
+}
+
+/// Puts back the ply `shrink` takes, for bounds on their way down.
+fn grow(bound: i32) -> i32 {
+  if bound.abs() >= SOLVED {bound} else {bound+bound.signum()}
+}
+
+impl QuantumSolver {
+  pub fn new() -> Self {
+    QuantumSolver::default()
+  }
+
+  /// Positions remembered so far.
+  pub fn len(&self) -> usize {
+    self.cache.len()
+  }
+
+  fn index<P: Player>(&mut self, player: P) -> u8 {
+    let uuid = player.get_uuid();
+    match self.players.iter().position(|p| *p == uuid) {
+      Some(i) => i as u8,
+      None => {
+        self.players.push(uuid);
+        (self.players.len()-1) as u8
+      },
+    }
+  }
+
+  fn key<P: Player>(&mut self, game: &QuantumTicTacToe<P>, side: P) -> Vec<u8> {
+    let (first,subscript) = game.get_round();
+    let head = vec!(self.index(side), self.index(game.get_player()), self.index(first), subscript);
+    let marks: Vec<Option<(u8,u8)>> = (0..9).map(|i| {
+      game.get_classic_mark(Position::from_cid(i)).map(|m| (self.index(m.player),m.subscript))
+    }).collect();
+    let ents: Vec<(u8,u8,u8,u8)> = game.get_ents().iter().map(|e| {
+      (e.p1.get_cid(),e.p2.get_cid(),self.index(e.player),e.subscript)
+    }).collect();
+    let collapse = game.get_collapse().map(|e| (e.p1.get_cid(),e.p2.get_cid()));
+    let mut best: Option<Vec<u8>> = None;
+    for t in 0..8 {
+      let mut key = head.clone();
+      let mut cells = [(0,0); 9];
+      for (i,mark) in marks.iter().enumerate() {
+        if let Some((p,s)) = mark {
+          cells[transform(t,i as u8) as usize] = (p+1,*s);
+        }
+      }
+      for (p,s) in cells.iter() {
+        key.push(*p);
+        key.push(*s);
+      }
+      let mut tents: Vec<(u8,u8,u8,u8)> = ents.iter().map(|&(a,b,p,s)| {
+        let (a,b) = (transform(t,a),transform(t,b));
+        (min(a,b),max(a,b),p,s)
+      }).collect();
+      tents.sort();
+      for (a,b,p,s) in tents {
+        key.extend_from_slice(&[a,b,p,s]);
+      }
+      if let Some((a,b)) = collapse {
+        key.push(transform(t,a));
+        key.push(transform(t,b));
+      }
+      if best.as_ref().map(|b| key < *b).unwrap_or(true) {
+        best = Some(key);
+      }
+    }
+    best.unwrap()
+  }
+
+  fn search<P: Player>(&mut self, game: &QuantumTicTacToe<P>, side: P, mut alpha: i32, mut beta: i32) -> Option<i32> {
+    self.nodes += 1;
+    if self.max_nodes.map(|n| self.nodes > n).unwrap_or(false) {
+      return None;
+    }
+    if self.nodes%1024 == 0 {
+      if let Some(deadline) = self.deadline {
+        if Instant::now() >= deadline {
+          return None;
+        }
+      }
+    }
+    let key = self.key(game,side);
+    if let Some(&(score,bound)) = self.cache.get(&key) {
+      match bound {
+        Bound::Exact => return Some(score),
+        Bound::Lower => alpha = max(alpha,score),
+        Bound::Upper => beta = min(beta,score),
+      }
+      if alpha >= beta {
+        return Some(score);
+      }
+    }
+    let (alpha0,beta0) = (alpha,beta);
+    let ours = game.get_player().is_teammate(&side);
+    let mut best = if ours {-INF} else {INF};
+    for m in game.get_valid_moves() {
+      let score = shrink(self.play(game,m,side,alpha,beta)?);
+      if ours {
+        best = max(best,score);
+        alpha = max(alpha,best);
+      } else {
+        best = min(best,score);
+        beta = min(beta,best);
+      }
+      if alpha >= beta {
+        break;
+      }
+    }
+    if best.abs() == INF {
+      best = 0;
+    }
+    let bound = if best <= alpha0 {
+      Bound::Upper
+    } else if best >= beta0 {
+      Bound::Lower
+    } else {
+      Bound::Exact
+    };
+    if self.cache.len() >= CACHE_LIMIT {
+      self.cache.clear();
+    }
+    self.cache.insert(key,(best,bound));
+    Some(best)
+  }
+
+  /// Score for `side` once `m` is played in `game`, before the ply is taken
+  /// off.
+  fn play<P: Player>(&mut self, game: &QuantumTicTacToe<P>, m: Rc<dyn Any>, side: P, alpha: i32, beta: i32) -> Option<i32> {
+    let mut next = game.clone();
+    match next.mov(m).result {
+      GameResult::Won(winner) => Some(if winner.is_teammate(&side) {SOLVED} else {-SOLVED}),
+      GameResult::Draw => Some(0),
+      GameResult::Incomplete => self.search(&next,side,grow(alpha),grow(beta)),
+    }
+  }
+
+  /// The best move for the player to move and what it leads to, or `None`
+  /// when the game is over or `budget` ran out first. A playout budget
+  /// counts positions looked at, so it gives up at the same point every
+  /// time.
+  pub fn solve<P: Player>(&mut self, game: &QuantumTicTacToe<P>, budget: Budget) -> Option<Solution> {
+    if let GameResult::Incomplete = game.check_win() {} else {
+      return None;
+    }
+    let (deadline,max_nodes) = match budget {
+      Budget::Time(t) => (Some(Instant::now()+t),None),
+      Budget::Playouts(n) => (None,Some(n)),
+    };
+    self.deadline = deadline;
+    self.max_nodes = max_nodes;
+    self.nodes = 0;
+    let side = game.get_player();
+    let mut best: Option<(Rc<dyn Any>,i32)> = None;
+    for m in game.get_valid_moves() {
+      let alpha = best.as_ref().map(|b| b.1).unwrap_or(-INF);
+      let score = shrink(self.play(game,Rc::clone(&m),side,alpha,INF)?);
+      if best.as_ref().map(|b| score > b.1).unwrap_or(true) {
+        best = Some((m,score));
+      }
+    }
+    best.map(|(mov,score)| Solution{mov, outcome: Outcome::from_score(score)})
+  }
+
+  /// What the position is worth to the player to move.
+  pub fn outcome<P: Player>(&mut self, game: &QuantumTicTacToe<P>, budget: Budget) -> Option<Outcome> {
+    self.solve(game,budget).map(|s| s.outcome)
+  }
+}
+
+/// Games the solver can find a sure move in. A game whole made of a
+/// quantum board is played perfectly. Inside bigger games searching a
+/// board to the end would assume the players keep taking turns in it, but
+/// the next move may well be sent somewhere else, so only a quantum board
+/// the player is sent to is looked at, for a move that wins it outright.
+/// That takes one look at each move, whatever the budget.
+pub trait Solve<T, P: Player> {
+  fn solve_in(&mut self, game: &T, budget: Budget) -> Option<Solution>;
+  /// A move that wins `game` for the player to move right away, in the
+  /// games `solve_in` looks into.
+  fn win_in(&mut self, game: &T) -> Option<Rc<dyn Any>>;
+}
+
+impl<P: Player> Solve<QuantumTicTacToe<P>,P> for QuantumSolver {
+  fn solve_in(&mut self, game: &QuantumTicTacToe<P>, budget: Budget) -> Option<Solution> {
+    self.solve(game,budget)
+  }
+  fn win_in(&mut self, game: &QuantumTicTacToe<P>) -> Option<Rc<dyn Any>> {
+    let mover = game.get_player();
+    game.get_valid_moves().into_iter().find(|m| {
+      let mut next = game.clone();
+      match next.mov(Rc::clone(m)).result {
+        GameResult::Won(winner) => winner.is_teammate(&mover),
+        _ => false,
+      }
+    })
+  }
+}
+
+impl<P: Player> Solve<TicTacToe<P>,P> for QuantumSolver {
+  fn solve_in(&mut self, _game: &TicTacToe<P>, _budget: Budget) -> Option<Solution> {
+    None
+  }
+  fn win_in(&mut self, _game: &TicTacToe<P>) -> Option<Rc<dyn Any>> {
+    None
+  }
+}
+
+impl<T: GenericGame<P>, P: Player> Solve<SuperTicTacToe<T,P>,P> for QuantumSolver where
+  QuantumSolver: Solve<T,P>
+{
+  /// The outcome is of the board won, not of the whole game.
+  fn solve_in(&mut self, game: &SuperTicTacToe<T,P>, _budget: Budget) -> Option<Solution> {
+    let mut boards = game.get_valid_moves().into_iter().filter_map(|m| m.downcast_ref::<SuperTicTacToeMove>().map(|m| m.pos));
+    let pos = boards.next()?;
+    if boards.any(|p| p != pos) {
+      return None;
+    }
+    let submove = self.win_in(game.get_cell_ref(pos).get_game_ref()?)?;
+    Some(Solution{mov: Rc::new(SuperTicTacToeMove{pos, submove}), outcome: Outcome::Win(1)})
+  }
+  fn win_in(&mut self, _game: &SuperTicTacToe<T,P>) -> Option<Rc<dyn Any>> {
+    None
+  }
+}
+
+impl<P: Player + 'static> Solve<Box<dyn DynGame<P>>,P> for QuantumSolver {
+  fn solve_in(&mut self, game: &Box<dyn DynGame<P>>, budget: Budget) -> Option<Solution> {
+    let game = (**game).as_any();
+    if let Some(game) = game.downcast_ref::<QuantumTicTacToe<P>>() {
+      self.solve_in(game,budget)
+    } else if let Some(game) = game.downcast_ref::<MixedSuperTicTacToe<P>>() {
+      self.solve_in(game,budget)
+    } else {
+      None
+    }
+  }
+  fn win_in(&mut self, game: &Box<dyn DynGame<P>>) -> Option<Rc<dyn Any>> {
+    let game = (**game).as_any();
+    if let Some(game) = game.downcast_ref::<QuantumTicTacToe<P>>() {
+      self.win_in(game)
+    } else {
+      None
+    }
+  }
+}
+
+impl<T: GenericGame<P>, P: Player> Solve<SuperDummyGame<T,P>,P> for QuantumSolver where
+  QuantumSolver: Solve<T,P>
+{
+  fn solve_in(&mut self, game: &SuperDummyGame<T,P>, budget: Budget) -> Option<Solution> {
+    self.solve_in(game.get_game_ref().get_game_ref()?,budget)
+  }
+  fn win_in(&mut self, game: &SuperDummyGame<T,P>) -> Option<Rc<dyn Any>> {
+    self.win_in(game.get_game_ref().get_game_ref()?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+  }
+
+  /// A quantum board with classic marks already in `xs` and `os`, X to
+  /// move.
+  fn board(xs: &[u8], os: &[u8]) -> QuantumTicTacToe<P> {
+    let mut game = QuantumTicTacToe::create(P::X, Position::from_cid(0));
+    for (cells,player) in vec![(xs,P::X),(os,P::O)] {
+      for &cid in cells.iter() {
+        game.place_mark(&[Position::from_cid(cid)], player).unwrap();
+      }
+    }
+    game
+  }
+
+  fn play(game: &mut QuantumTicTacToe<P>, text: &str) -> GameResult<P> {
+    let m = find_move(&game.get_valid_moves(), text).unwrap();
+    game.mov(m).result
+  }
+
+  /// Plain minimax with the solver's scoring, to check it against.
+  fn minimax(game: &QuantumTicTacToe<P>, side: P) -> i32 {
+    let ours = game.get_player() == side;
+    let scores = game.get_valid_moves().into_iter().map(|m| {
+      let mut next = game.clone();
+      shrink(match next.mov(m).result {
+        GameResult::Won(winner) => if winner == side {SOLVED} else {-SOLVED},
+        GameResult::Draw => 0,
+        GameResult::Incomplete => minimax(&next, side),
+      })
+    });
+    if ours {scores.max().unwrap_or(0)} else {scores.min().unwrap_or(0)}
+  }
+
+  #[test]
+  fn agrees_with_minimax() {
+    let positions = vec![
+      board(&[0,8,6], &[4,2]),
+      board(&[1,5,6], &[4,8]),
+      board(&[0,5,7], &[2,4]),
+      board(&[1,3,8], &[0,4]),
+    ];
+    let mut solver = QuantumSolver::new();
+    for game in positions.iter() {
+      let expected = Outcome::from_score(minimax(game, P::X));
+      assert_eq!(solver.outcome(game, Budget::Playouts(u64::max_value())), Some(expected));
+    }
+  }
+
+  /// X marks 2 5, O 5 7 and X 2 7, closing a cycle for O to collapse,
+  /// with O's classic marks in `os`.
+  fn cycle(os: &[u8]) -> QuantumTicTacToe<P> {
+    let mut game = board(&[3,4], os);
+    for text in vec!["q2 5", "q5 7", "q2 7"] {
+      assert!(matches!(play(&mut game, text), GameResult::Incomplete));
+    }
+    assert!(game.get_collapse().is_some());
+    game
+  }
+
+  #[test]
+  fn collapse_makes_a_row() {
+    // X's last mark in 2 pushes its first into 5, next to 3 and 4
+    let mut game = cycle(&[0,8]);
+    assert!(play(&mut game, "m1").is_won_by(P::X));
+    assert!(QuantumSolver::new().solve(&game, Budget::Playouts(1000)).is_none());
+  }
+
+  #[test]
+  fn collapse_makes_rows_for_both() {
+    // the same collapse puts O's middle mark in 7, between 6 and 8
+    let mut game = cycle(&[6,8]);
+    assert!(matches!(play(&mut game, "m1"), GameResult::Draw));
+  }
+
+  #[test]
+  fn collapses_away_from_a_loss() {
+    let game = cycle(&[0,8]);
+    let solution = solver_move(&game);
+    assert_eq!(move_notation(&solution.mov), "m2");
+    assert_eq!(solution.outcome, Outcome::from_score(minimax(&game, P::O)));
+  }
+
+  #[test]
+  fn takes_boards_won_outright() {
+    // for O, m1 puts its middle mark in 7, between 6 and 8
+    let mut game = board(&[0], &[6,8]);
+    for text in vec!["q2 5", "q5 7", "q2 7"] {
+      play(&mut game, text);
+    }
+    let m = QuantumSolver::new().win_in(&game).unwrap();
+    assert_eq!(move_notation(&m), "m1");
+    // while here either collapse only helps X
+    assert!(QuantumSolver::new().win_in(&cycle(&[0,8])).is_none());
+  }
+
+  fn solver_move(game: &QuantumTicTacToe<P>) -> Solution {
+    QuantumSolver::new().solve(game, Budget::Playouts(u64::max_value())).unwrap()
+  }
+}
@@ -1,2 +1,4 @@
 mod tic_tac_toe;
-pub use tic_tac_toe::*;
\ No newline at end of file
+mod bot;
+pub use tic_tac_toe::*;
+pub use bot::*;
@@ -3,11 +3,23 @@ use std::rc::Rc;
 use std::cell::RefCell;
 
 mod generic;
+mod board;
 mod super_tic_tac_toe;
 mod quantum_tic_tac_toe;
+mod classic_tic_tac_toe;
+mod dyn_game;
+mod clock;
+mod setup;
+mod notation;
 
 pub use quantum_tic_tac_toe::*;
 pub use super_tic_tac_toe::*;
+pub use classic_tic_tac_toe::*;
+pub use board::*;
+pub use dyn_game::*;
+pub use clock::*;
+pub use setup::*;
+pub use notation::*;
 pub use generic::*;
 
 #[derive(Clone,Debug)]
@@ -29,7 +41,8 @@ impl<P: Player> GenericGame<P> for DummyGame<P> {
     MoveData {
       jump: None,
       next_player: self.player,
-      result: GameResult::Won(player)
+      result: GameResult::Won(player),
+      events: vec!(GameEvent::GameOver(GameResult::Won(player)))
     }
   }
   
@@ -39,6 +52,9 @@ impl<P: Player> GenericGame<P> for DummyGame<P> {
   fn get_valid_moves(&self) -> Vec<Rc<dyn Any>> {
     vec!(Rc::new(true))
   }
+  fn is_same_move(&self, _a: &Rc<dyn Any>, _b: &Rc<dyn Any>) -> bool {
+    true
+  }
   
   fn set_player(&mut self, player: P) {
     self.player = player;
@@ -48,6 +64,18 @@ impl<P: Player> GenericGame<P> for DummyGame<P> {
   }
 }
 
+impl<P: Player> GameSetup<P> for DummyGame<P> {
+  fn place_mark(&mut self, _path: &[Position], _player: P) -> Result<GameResult<P>,String> {
+    Err(String::from("dummy games have no cells"))
+  }
+  fn decide(&mut self, _path: &[Position], _result: GameResult<P>) -> Result<GameResult<P>,String> {
+    Err(String::from("dummy games have no cells"))
+  }
+  fn set_allowed(&mut self, _path: &[Position], _cells: Vec<Position>) -> Result<(),String> {
+    Err(String::from("only ultimate boards have allowed boards"))
+  }
+}
+
 #[derive(Clone)]
 pub struct SuperDummyGame<T: GenericGame<P>,P: Player> {game: SubgameState<T,P>}
 
@@ -58,6 +86,15 @@ impl<T:GenericGame<P>,P: Player> SuperDummyGame<T,P> {
   pub fn get_game_ref(&self) -> &SubgameState<T,P> {
     &self.game
   }
+  /// Ends the game from outside, like when a flag falls.
+  pub fn end(&mut self, result: GameResult<P>) {
+    let state = std::mem::replace(&mut self.game,SubgameState::None);
+    self.game = match (state,result) {
+      (SubgameState::Playing(game),GameResult::Won(player)) => SubgameState::Won(player,game),
+      (SubgameState::Playing(game),GameResult::Draw) => SubgameState::Draw(game),
+      (state,_) => state,
+    };
+  }
 }
 
 impl<T:GenericGame<P>,P: Player> GenericGame<P> for SuperDummyGame<T,P> {
@@ -99,6 +136,13 @@ impl<T:GenericGame<P>,P: Player> GenericGame<P> for SuperDummyGame<T,P> {
       vec!()
     }
   }
+  fn is_same_move(&self, a: &Rc<dyn Any>, b: &Rc<dyn Any>) -> bool {
+    if let SubgameState::<T,P>::Playing(game) = &self.game {
+      game.is_same_move(a,b)
+    } else {
+      false
+    }
+  }
   
   fn set_player(&mut self, player: P) {
     if let SubgameState::<T,P>::Playing(game) = &mut self.game {
@@ -0,0 +1,247 @@
+use super::*;
+
+/// Something that can sit in a `Board` cell and may belong to a player.
+pub trait BoardCell<P: Player> {
+    /// The player this cell counts for when looking for lines.
+    fn owner(&self) -> Option<P>;
+    /// Whether this cell can still change. A board with no open cells is full.
+    fn is_open(&self) -> bool;
+}
+
+impl<P: Player> BoardCell<P> for Option<P> {
+    fn owner(&self) -> Option<P> {
+        *self
+    }
+    fn is_open(&self) -> bool {
+        self.is_none()
+    }
+}
+
+impl<T: GenericGame<P>, P: Player> BoardCell<P> for SubgameState<T,P> {
+    fn owner(&self) -> Option<P> {
+        if let SubgameState::Won(player,_) = self {
+            Some(*player)
+        } else {
+            None
+        }
+    }
+    fn is_open(&self) -> bool {
+        if let SubgameState::Playing(_) = self {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A square grid of cells that knows which runs of cells form a winning line.
+///
+/// `size` is the side of the grid and `line` is how many cells in a row are
+/// needed to win. Classic tic tac toe is `Board::new(3,3,...)`.
+#[derive(Clone, Debug)]
+pub struct Board<C> {
+    cells: Vec<C>,
+    size: u8,
+    line: u8,
+}
+
+impl<C> Board<C> {
+    pub fn new<F: FnMut(u8,u8) -> C>(size: u8, line: u8, mut new_cell: F) -> Self {
+        assert!(
+            line > 0 && line <= size,
+            "line length {} does not fit a board of size {}", line, size
+        );
+        let mut cells = Vec::with_capacity((size as usize)*(size as usize));
+        for y in 0..size {
+            for x in 0..size {
+                cells.push(new_cell(x,y));
+            }
+        }
+        Board{cells, size, line}
+    }
+
+    pub fn get_size(&self) -> u8 {
+        self.size
+    }
+    pub fn get_line_len(&self) -> u8 {
+        self.line
+    }
+
+    fn index(&self, x: u8, y: u8) -> usize {
+        assert!(
+            x < self.size && y < self.size,
+            "vector ({},{}) is out of board bounds!", x, y
+        );
+        (y as usize)*(self.size as usize)+(x as usize)
+    }
+
+    pub fn get_xy(&self, x: u8, y: u8) -> &C {
+        &self.cells[self.index(x,y)]
+    }
+    pub fn get_xy_mut(&mut self, x: u8, y: u8) -> &mut C {
+        let i = self.index(x,y);
+        &mut self.cells[i]
+    }
+    pub fn get(&self, pos: Position) -> &C {
+        self.get_xy(pos.get_x(),pos.get_y())
+    }
+    pub fn get_mut(&mut self, pos: Position) -> &mut C {
+        self.get_xy_mut(pos.get_x(),pos.get_y())
+    }
+    pub fn set(&mut self, pos: Position, cell: C) -> C {
+        std::mem::replace(self.get_mut(pos),cell)
+    }
+
+    pub fn cells(&self) -> std::slice::Iter<'_, C> {
+        self.cells.iter()
+    }
+
+    /// Every horizontal, vertical and diagonal run of `line` cells, as
+    /// lists of `(x,y)` coordinates.
+    pub fn lines(&self) -> Vec<Vec<(u8,u8)>> {
+        let (size,line) = (self.size as i16,self.line as i16);
+        let mut lines = vec!();
+        for &(dx,dy) in [(1,0),(0,1),(1,1),(-1,1)].iter() {
+            for y in 0..size {
+                for x in 0..size {
+                    let (ex,ey) = (x+dx*(line-1),y+dy*(line-1));
+                    if ex < 0 || ex >= size || ey >= size {
+                        continue;
+                    }
+                    let mut l = Vec::with_capacity(line as usize);
+                    for i in 0..line {
+                        l.push(((x+dx*i) as u8,(y+dy*i) as u8));
+                    }
+                    lines.push(l);
+                }
+            }
+        }
+        lines
+    }
+}
+
+impl<C> Board<C> {
+    /// The owner of the first cell of `line`, if the whole line belongs to
+    /// them or their teammates.
+    pub fn owner_of_line<P: Player>(&self, line: &[(u8,u8)]) -> Option<P> where C: BoardCell<P> {
+        let mut owner: Option<P> = None;
+        for &(x,y) in line.iter() {
+            let player = self.get_xy(x,y).owner()?;
+            if let Some(owner) = owner {
+                if !owner.is_teammate(&player) {
+                    return None;
+                }
+            }
+            owner = owner.or(Some(player));
+        }
+        owner
+    }
+
+    pub fn is_full<P: Player>(&self) -> bool where C: BoardCell<P> {
+        !self.cells.iter().any(|c| c.is_open())
+    }
+
+    /// Won by the owner of the first complete line, drawn when full,
+    /// incomplete otherwise.
+    pub fn check_win<P: Player>(&self) -> GameResult<P> where C: BoardCell<P> {
+        for line in self.lines() {
+            if let Some(player) = self.owner_of_line(&line) {
+                return GameResult::Won(player);
+            }
+        }
+        if self.is_full::<P>() {
+            GameResult::Draw
+        } else {
+            GameResult::Incomplete
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// X and O play alone, T plays with X.
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum P {X, O, T}
+
+    impl Player for P {
+        fn next_player(&self) -> Self {
+            match self {P::X => P::O, P::O => P::T, P::T => P::X}
+        }
+        fn get_uuid(&self) -> String {
+            format!("{:?}", self)
+        }
+        fn get_team(&self) -> String {
+            String::from(if *self == P::O {"O"} else {"X"})
+        }
+    }
+
+    /// A board with `rows` written out, `.` for an empty cell.
+    fn board(line: u8, rows: &[&str]) -> Board<Option<P>> {
+        Board::new(rows.len() as u8, line, |x, y| {
+            match rows[y as usize].as_bytes()[x as usize] {
+                b'X' => Some(P::X),
+                b'O' => Some(P::O),
+                b'T' => Some(P::T),
+                _ => None,
+            }
+        })
+    }
+
+    fn empty(size: u8, line: u8) -> Board<Option<P>> {
+        Board::new(size, line, |_, _| None)
+    }
+
+    fn result(b: &Board<Option<P>>) -> String {
+        format!("{:?}", b.check_win::<P>())
+    }
+
+    #[test]
+    fn line_counts() {
+        assert_eq!(empty(3, 3).lines().len(), 8);
+        assert_eq!(empty(4, 4).lines().len(), 10);
+        assert_eq!(empty(4, 3).lines().len(), 24);
+        assert_eq!(empty(5, 4).lines().len(), 28);
+        assert_eq!(empty(3, 2).lines().len(), 20);
+    }
+
+    #[test]
+    fn lines_stay_on_the_board() {
+        for &(size, line) in [(3, 3), (4, 3), (5, 4), (3, 1)].iter() {
+            for l in empty(size, line).lines() {
+                assert_eq!(l.len(), line as usize);
+                assert!(l.iter().all(|&(x, y)| x < size && y < size));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn line_longer_than_board() {
+        empty(3, 4);
+    }
+
+    #[test]
+    fn owners_of_lines() {
+        let b = board(3, &["XTX", "OO.", "XOX"]);
+        assert_eq!(b.owner_of_line::<P>(&[(0,0),(1,0),(2,0)]), Some(P::X));
+        assert_eq!(b.owner_of_line::<P>(&[(0,1),(1,1),(2,1)]), None);
+        assert_eq!(b.owner_of_line::<P>(&[(0,2),(1,2),(2,2)]), None);
+        assert_eq!(b.owner_of_line::<P>(&[(1,0),(1,1),(1,2)]), None);
+        let t = board(3, &["T..", "X..", "X.."]);
+        assert_eq!(t.owner_of_line::<P>(&[(0,0),(0,1),(0,2)]), Some(P::T));
+    }
+
+    #[test]
+    fn wins_and_draws() {
+        assert_eq!(result(&board(3, &["X..", ".X.", "..X"])), "Won(X)");
+        assert_eq!(result(&board(3, &["..O", ".O.", "O.."])), "Won(O)");
+        assert_eq!(result(&board(3, &["XOX", "XOO", "OXX"])), "Draw");
+        assert_eq!(result(&board(3, &["XOX", "XO.", "OXO"])), "Incomplete");
+        assert_eq!(board(3, &["X..", "...", "..."]).is_full::<P>(), false);
+        assert_eq!(board(3, &["XOX", "XOO", "OXX"]).is_full::<P>(), true);
+        assert_eq!(result(&board(4, &["XXX.", "OOO.", "....", "...."])), "Incomplete");
+        assert_eq!(result(&board(3, &["XXX.", "OOO.", "....", "...."])), "Won(X)");
+    }
+}
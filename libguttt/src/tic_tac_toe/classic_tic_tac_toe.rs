@@ -0,0 +1,96 @@
+use super::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct TicTacToeMove {
+  pub pos: Position,
+}
+
+/// Plain tic tac toe. Marking a cell sends the next player to the board
+/// at the same position one level up.
+#[derive(Clone)]
+pub struct TicTacToe<P: Player> {
+  marks: Board<Option<P>>,
+  player: P,
+}
+
+impl<P: Player> TicTacToe<P> {
+  pub fn new(player: P) -> Self {
+    TicTacToe{
+      marks: Board::new(3,3,|_,_| None),
+      player,
+    }
+  }
+  pub fn get_mark(&self, pos: Position) -> Option<P> {
+    *self.marks.get(pos)
+  }
+  pub fn get_board(&self) -> &Board<Option<P>> {
+    &self.marks
+  }
+}
+
+impl<P: Player> GenericGame<P> for TicTacToe<P> {
+  fn create(player: P, _pos: Position) -> Self {
+    Self::new(player)
+  }
+  fn mov(&mut self, m: Rc<dyn Any>) -> MoveData<P> {
+    let mo = m.downcast_ref::<TicTacToeMove>().expect(
+        "type mismatch: TicTacToe expects TicTacToeMove, found I don't care what",
+    );
+    assert!(self.is_valid_move(Rc::clone(&m)),"invalid move!");
+    self.marks.set(mo.pos,Some(self.player));
+    self.player = self.player.next_player();
+    let result = self.marks.check_win();
+    MoveData {
+      jump: Some(vec![mo.pos]),
+      next_player: self.player,
+      result,
+      events: GameEvent::game_over(&result)
+    }
+  }
+  fn get_valid_moves(&self) -> Vec<Rc<dyn Any>> {
+    let mut v: Vec<Rc<dyn Any>> = Vec::with_capacity(9);
+    for i in 0..9 {
+      let pos = Position::from_cid(i as u8);
+      if self.get_mark(pos).is_none() {
+        v.push(Rc::new(TicTacToeMove{pos}));
+      }
+    }
+    v
+  }
+  fn is_valid_move(&self, m: Rc<dyn Any>) -> bool {
+    if let Some(mo) = m.downcast_ref::<TicTacToeMove>() {
+      self.get_mark(mo.pos).is_none()
+    } else {false}
+  }
+  fn is_same_move(&self, a: &Rc<dyn Any>, b: &Rc<dyn Any>) -> bool {
+    match (a.downcast_ref::<TicTacToeMove>(),b.downcast_ref::<TicTacToeMove>()) {
+      (Some(a),Some(b)) => a.pos == b.pos,
+      _ => false
+    }
+  }
+  fn set_player(&mut self, player: P) {
+    self.player = player;
+  }
+  fn get_player(&self) -> P {
+    self.player
+  }
+}
+
+impl<P: Player> GameSetup<P> for TicTacToe<P> {
+  fn place_mark(&mut self, path: &[Position], player: P) -> Result<GameResult<P>,String> {
+    match path {
+      [pos] if self.get_mark(*pos).is_none() => {
+        self.marks.set(*pos,Some(player));
+        Ok(self.marks.check_win())
+      },
+      [pos] => Err(format!("cell {} is already marked", pos.get_cid())),
+      _ => Err(String::from("tic tac toe cells hold marks, not boards")),
+    }
+  }
+  fn decide(&mut self, _path: &[Position], _result: GameResult<P>) -> Result<GameResult<P>,String> {
+    Err(String::from("tic tac toe cells hold marks, not boards"))
+  }
+  fn set_allowed(&mut self, _path: &[Position], _cells: Vec<Position>) -> Result<(),String> {
+    Err(String::from("only ultimate boards have allowed boards"))
+  }
+}
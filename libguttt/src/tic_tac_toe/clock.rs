@@ -0,0 +1,120 @@
+use super::*;
+use std::time::{Duration, Instant};
+
+/// Starting time on every clock, and time added after each move.
+#[derive(Copy, Clone, Debug)]
+pub struct TimeControl {
+  pub base: Duration,
+  pub increment: Duration,
+}
+
+impl TimeControl {
+  /// Parses `BASE[+INCREMENT]` in seconds, like `300+5`.
+  pub fn parse(s: &str) -> Result<TimeControl,String> {
+    let secs = |s: &str| -> Result<Duration,String> {
+      let n: f64 = s.trim().parse().map_err(|_| format!("'{}' is not a number of seconds", s))?;
+      if n < 0.0 || !n.is_finite() {
+        return Err(format!("'{}' is not a number of seconds", s));
+      }
+      Ok(Duration::from_millis((n*1000.0) as u64))
+    };
+    let mut parts = s.splitn(2, '+');
+    let base = secs(parts.next().unwrap_or(""))?;
+    let increment = match parts.next() {
+      Some(inc) => secs(inc)?,
+      None => Duration::from_secs(0),
+    };
+    Ok(TimeControl{base, increment})
+  }
+}
+
+/// Chess clocks for every player of a game. Only the player to move has
+/// their clock running.
+#[derive(Clone, Debug)]
+pub struct GameClock<P: Player> {
+  control: TimeControl,
+  players: Vec<P>,
+  remaining: Vec<Duration>,
+  running: Option<(usize,Instant)>,
+}
+
+impl<P: Player> GameClock<P> {
+  pub fn new(players: Vec<P>, control: TimeControl) -> Self {
+    let remaining = vec![control.base; players.len()];
+    GameClock{control, players, remaining, running: None}
+  }
+
+  pub fn get_control(&self) -> TimeControl {
+    self.control
+  }
+
+  fn index(&self, player: P) -> usize {
+    self.players.iter().position(|p| p.get_uuid() == player.get_uuid())
+      .expect("player has no clock")
+  }
+
+  /// Starts `player`'s clock, stopping whoever's was running without
+  /// giving them the increment.
+  pub fn start(&mut self, player: P) {
+    self.pause();
+    self.running = Some((self.index(player), Instant::now()));
+  }
+
+  /// Stops the running clock without giving the increment.
+  pub fn pause(&mut self) {
+    if let Some((i,since)) = self.running.take() {
+      self.remaining[i] = self.remaining[i].checked_sub(since.elapsed()).unwrap_or(Duration::from_secs(0));
+    }
+  }
+
+  /// Stops the running clock after a move and adds the increment, unless
+  /// the flag has already fallen.
+  pub fn stop(&mut self) {
+    if let Some((i,_)) = self.running {
+      self.pause();
+      if self.remaining[i] > Duration::from_secs(0) {
+        self.remaining[i] += self.control.increment;
+      }
+    }
+  }
+
+  pub fn get_running(&self) -> Option<P> {
+    self.running.map(|(i,_)| self.players[i])
+  }
+
+  pub fn remaining(&self, player: P) -> Duration {
+    let i = self.index(player);
+    match self.running {
+      Some((r,since)) if r == i => {
+        self.remaining[i].checked_sub(since.elapsed()).unwrap_or(Duration::from_secs(0))
+      },
+      _ => self.remaining[i],
+    }
+  }
+
+  /// The first player out of time.
+  pub fn flagged(&self) -> Option<P> {
+    for p in self.players.iter() {
+      if self.remaining(*p) == Duration::from_secs(0) {
+        return Some(*p);
+      }
+    }
+    None
+  }
+
+  /// Flag fall loses the game. If everyone else is one team, they win;
+  /// with more sides left the game is called a draw.
+  pub fn result(&self) -> GameResult<P> {
+    if let Some(flagged) = self.flagged() {
+      let rest: Vec<P> = self.players.iter().cloned()
+        .filter(|p| !p.is_teammate(&flagged)).collect();
+      if rest.len() > 0 && rest.iter().all(|p| p.is_teammate(&rest[0])) {
+        GameResult::Won(rest[0])
+      } else {
+        GameResult::Draw
+      }
+    } else {
+      GameResult::Incomplete
+    }
+  }
+}
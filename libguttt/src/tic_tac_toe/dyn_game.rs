@@ -0,0 +1,195 @@
+use super::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// Object-safe face of `GenericGame`, so games of different types can be
+/// boxed and mixed at runtime.
+pub trait DynGame<P: Player>: GenericGame<P> + GameSetup<P> + Send {
+  fn clone_box(&self) -> Box<dyn DynGame<P>>;
+  fn as_any(&self) -> &dyn Any;
+}
+
+impl<T, P> DynGame<P> for T where
+  T: GenericGame<P> + GameSetup<P> + Clone + Send + 'static,
+  P: Player + 'static
+{
+  fn clone_box(&self) -> Box<dyn DynGame<P>> {
+    Box::new(self.clone())
+  }
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+}
+
+impl<P: Player + 'static> Clone for Box<dyn DynGame<P>> {
+  fn clone(&self) -> Self {
+    (**self).clone_box()
+  }
+}
+
+impl<P: Player + 'static> GenericGame<P> for Box<dyn DynGame<P>> {
+  fn create(_player: P, _pos: Position) -> Self {
+    panic!("boxed games don't know what they are, build them from a GameSpec");
+  }
+  fn mov(&mut self, m: Rc<dyn Any>) -> MoveData<P> {
+    (**self).mov(m)
+  }
+  fn get_valid_moves(&self) -> Vec<Rc<dyn Any>> {
+    (**self).get_valid_moves()
+  }
+  fn is_valid_move(&self, m: Rc<dyn Any>) -> bool {
+    (**self).is_valid_move(m)
+  }
+  fn is_same_move(&self, a: &Rc<dyn Any>, b: &Rc<dyn Any>) -> bool {
+    (**self).is_same_move(a,b)
+  }
+  fn set_player(&mut self, player: P) {
+    (**self).set_player(player)
+  }
+  fn get_player(&self) -> P {
+    (**self).get_player()
+  }
+}
+
+/// `SuperTicTacToe` whose cells may each be a different kind of game.
+pub type MixedSuperTicTacToe<P> = SuperTicTacToe<Box<dyn DynGame<P>>,P>;
+
+/// Shape of a game tree, written like `S(S(T))`.
+///
+/// * `S(x)` - `SuperTicTacToe` with `x` in every cell
+/// * `S(a,b,c,d,e,f,g,h,i)` - `SuperTicTacToe` with its own game in each
+///   cell, row by row from the top left
+/// * `Q` - `QuantumTicTacToe`
+/// * `T` - classic `TicTacToe`
+/// * `D` - `DummyGame`, won by whoever moves first
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameSpec {
+  Super(Box<GameSpec>),
+  Mixed(Vec<GameSpec>),
+  Quantum,
+  Classic,
+  Dummy,
+}
+
+impl GameSpec {
+  pub fn build<P: Player + Send + 'static>(&self, player: P) -> Box<dyn DynGame<P>> {
+    match self {
+      GameSpec::Super(sub) => {
+        let sub = (**sub).clone();
+        Box::new(MixedSuperTicTacToe::new(player, move |_pos: Position, player: P| {
+          sub.build(player)
+        }))
+      },
+      GameSpec::Mixed(cells) => {
+        let cells = cells.clone();
+        Box::new(MixedSuperTicTacToe::new(player, move |pos: Position, player: P| {
+          cells[pos.get_cid() as usize].build(player)
+        }))
+      },
+      GameSpec::Quantum => Box::new(QuantumTicTacToe::new(player)),
+      GameSpec::Classic => Box::new(TicTacToe::new(player)),
+      GameSpec::Dummy => Box::new(DummyGame::new(player)),
+    }
+  }
+
+  fn parse(s: &[char], i: &mut usize) -> Result<GameSpec,String> {
+    let c = *s.get(*i).ok_or_else(|| String::from("unexpected end of game spec"))?;
+    *i += 1;
+    match c.to_ascii_uppercase() {
+      'S' => {
+        Self::expect(s, i, '(')?;
+        let mut cells = vec!(Self::parse(s, i)?);
+        while s.get(*i) == Some(&',') {
+          *i += 1;
+          cells.push(Self::parse(s, i)?);
+        }
+        Self::expect(s, i, ')')?;
+        match cells.len() {
+          1 => Ok(GameSpec::Super(Box::new(cells.pop().unwrap()))),
+          9 => Ok(GameSpec::Mixed(cells)),
+          n => Err(format!("S takes 1 or 9 games, found {}", n)),
+        }
+      },
+      'Q' => Ok(GameSpec::Quantum),
+      'T' => Ok(GameSpec::Classic),
+      'D' => Ok(GameSpec::Dummy),
+      c => Err(format!("unknown game '{}' at {}", c, *i-1)),
+    }
+  }
+
+  fn expect(s: &[char], i: &mut usize, c: char) -> Result<(),String> {
+    match s.get(*i) {
+      Some(&f) if f == c => {*i += 1; Ok(())},
+      Some(f) => Err(format!("expected '{}' at {}, found '{}'", c, *i, f)),
+      None => Err(format!("expected '{}' at {}, found end of game spec", c, *i)),
+    }
+  }
+}
+
+impl FromStr for GameSpec {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self,String> {
+    let s: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut i = 0;
+    let spec = Self::parse(&s, &mut i)?;
+    if i < s.len() {
+      return Err(format!("trailing '{}' at {}", s[i..].iter().collect::<String>(), i));
+    }
+    Ok(spec)
+  }
+}
+
+impl fmt::Display for GameSpec {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GameSpec::Super(sub) => write!(f, "S({})", sub),
+      GameSpec::Mixed(cells) => {
+        write!(f, "S(")?;
+        for (i,cell) in cells.iter().enumerate() {
+          if i > 0 {
+            write!(f, ",")?;
+          }
+          write!(f, "{}", cell)?;
+        }
+        write!(f, ")")
+      },
+      GameSpec::Quantum => write!(f, "Q"),
+      GameSpec::Classic => write!(f, "T"),
+      GameSpec::Dummy => write!(f, "D"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn spec(s: &str) -> Result<GameSpec,String> {
+    s.parse()
+  }
+
+  #[test]
+  fn specs_read_back() {
+    assert_eq!(spec("Q"), Ok(GameSpec::Quantum));
+    assert_eq!(spec("s(t)"), Ok(GameSpec::Super(Box::new(GameSpec::Classic))));
+    assert_eq!(spec(" S ( S(D) ) "), Ok(GameSpec::Super(Box::new(GameSpec::Super(Box::new(GameSpec::Dummy))))));
+    for s in ["T", "S(Q)", "S(S(T))", "S(T,Q,D,T,S(Q),T,Q,D,T)"].iter() {
+      assert_eq!(spec(s).unwrap().to_string(), *s);
+    }
+    match spec("S(T,Q,D,T,S(Q),T,Q,D,T)") {
+      Ok(GameSpec::Mixed(cells)) => assert_eq!(cells[4], GameSpec::Super(Box::new(GameSpec::Quantum))),
+      other => panic!("wtf? {:?}", other),
+    }
+  }
+
+  #[test]
+  fn malformed_specs() {
+    assert_eq!(spec(""), Err(String::from("unexpected end of game spec")));
+    assert_eq!(spec("X"), Err(String::from("unknown game 'X' at 0")));
+    assert_eq!(spec("S(T"), Err(String::from("expected ')' at 3, found end of game spec")));
+    assert_eq!(spec("ST"), Err(String::from("expected '(' at 1, found 'T'")));
+    assert_eq!(spec("S()"), Err(String::from("unknown game ')' at 2")));
+    assert_eq!(spec("S(T,Q)"), Err(String::from("S takes 1 or 9 games, found 2")));
+    assert_eq!(spec("S(T)Q"), Err(String::from("trailing 'Q' at 4")));
+  }
+}
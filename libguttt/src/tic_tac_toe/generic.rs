@@ -4,10 +4,12 @@ use std::cell::RefCell;
 use std::ops::{Deref, DerefMut};
 
 pub trait GenericGame<P: Player> {
-    fn create(player: P, pos: Position) -> Self;
+    fn create(player: P, pos: Position) -> Self where Self: Sized;
     fn mov(&mut self, m: Rc<dyn Any>) -> MoveData<P>;
     fn get_valid_moves(&self) -> Vec<Rc<dyn Any>>;
     fn is_valid_move(&self, m: Rc<dyn Any>) -> bool;
+    /// Whether `a` and `b` are the same move in the current position.
+    fn is_same_move(&self, a: &Rc<dyn Any>, b: &Rc<dyn Any>) -> bool;
     fn set_player(&mut self, player: P);
     fn get_player(&self) -> P;
 }
@@ -80,6 +82,14 @@ impl Position {
 pub trait Player: Copy {
     fn next_player(&self) -> Self;
     fn get_uuid(&self) -> String;
+    /// Players with the same team share their lines, so a row made of
+    /// teammates' marks wins for all of them. Everyone plays alone by default.
+    fn get_team(&self) -> String {
+        self.get_uuid()
+    }
+    fn is_teammate(&self, other: &Self) -> bool {
+        self.get_team() == other.get_team()
+    }
 }
 
 #[derive(Debug)]
@@ -87,8 +97,79 @@ pub struct MoveData<P: Player> {
     pub jump: Option<Vec<Position>>,
     pub next_player: P,
     pub result: GameResult<P>,
+    pub events: Vec<GameEvent<P>>,
+}
+
+/// Something notable that happened during a move.
+///
+/// Paths lead from the board that made the move down to the board the event
+/// is about, so an empty path means the game itself.
+#[derive(Debug, Clone)]
+pub enum GameEvent<P: Player> {
+    SubboardWon(Vec<Position>, P),
+    SubboardDrawn(Vec<Position>),
+    /// The boards the next move may be played in changed.
+    JumpChanged(Vec<Position>, Vec<Position>),
+    /// Cells that collapsed into classic marks.
+    QuantumCollapse(Vec<Position>, Vec<Position>),
+    GameOver(GameResult<P>),
+}
+
+impl<P: Player> GameEvent<P> {
+    /// `GameOver` if `result` ends the game, nothing otherwise.
+    pub fn game_over(result: &GameResult<P>) -> Vec<GameEvent<P>> {
+        match result {
+            GameResult::Incomplete => vec!(),
+            result => vec!(GameEvent::GameOver(*result)),
+        }
+    }
+
+    /// The same event as seen from the board one level up, which played it
+    /// in its cell at `pos`. A finished subgame becomes a decided subboard.
+    pub fn nest(self, pos: Position) -> GameEvent<P> {
+        let prefix = |mut path: Vec<Position>| {
+            path.insert(0, pos);
+            path
+        };
+        match self {
+            GameEvent::SubboardWon(path, player) => GameEvent::SubboardWon(prefix(path), player),
+            GameEvent::SubboardDrawn(path) => GameEvent::SubboardDrawn(prefix(path)),
+            GameEvent::JumpChanged(path, jump) => GameEvent::JumpChanged(prefix(path), jump),
+            GameEvent::QuantumCollapse(path, cells) => GameEvent::QuantumCollapse(prefix(path), cells),
+            GameEvent::GameOver(GameResult::Won(player)) => GameEvent::SubboardWon(vec![pos], player),
+            GameEvent::GameOver(GameResult::Draw) => GameEvent::SubboardDrawn(vec![pos]),
+            GameEvent::GameOver(GameResult::Incomplete) => GameEvent::GameOver(GameResult::Incomplete),
+        }
+    }
+}
+
+/// Listeners for the events of the moves fed to it. Kept apart from the
+/// games, so clones made to think ahead don't report anything.
+pub struct GameEvents<P: Player> {
+    listeners: Vec<Box<dyn FnMut(&GameEvent<P>) + Send>>,
+}
+
+impl<P: Player> GameEvents<P> {
+    pub fn new() -> Self {
+        GameEvents{listeners: vec!()}
+    }
+    pub fn listen<F: FnMut(&GameEvent<P>) + Send + 'static>(&mut self, f: F) {
+        self.listeners.push(Box::new(f));
+    }
+    pub fn emit(&mut self, mdata: &MoveData<P>) {
+        for event in mdata.events.iter() {
+            self.emit_event(event);
+        }
+    }
+    pub fn emit_event(&mut self, event: &GameEvent<P>) {
+        for listener in self.listeners.iter_mut() {
+            listener(event);
+        }
+    }
 }
 
+/// `Won` holds one of the winners. When a line was made by a team, the
+/// rest of the team won too, see `winning_team`.
 #[derive(Debug, Copy, Clone)]
 pub enum GameResult<P: Player> {
     Won(P),
@@ -96,6 +177,24 @@ pub enum GameResult<P: Player> {
     Incomplete,
 }
 
+impl<P: Player> GameResult<P> {
+    pub fn winning_team(&self) -> Option<String> {
+        if let GameResult::Won(player) = self {
+            Some(player.get_team())
+        } else {
+            None
+        }
+    }
+    /// Whether `player` is among the winners.
+    pub fn is_won_by(&self, player: P) -> bool {
+        if let GameResult::Won(winner) = self {
+            winner.is_teammate(&player)
+        } else {
+            false
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum SubgameState<T: GenericGame<P>,P: Player> {
     Won(P,T),
@@ -104,6 +203,40 @@ pub enum SubgameState<T: GenericGame<P>,P: Player> {
     None
 }
 
+/// What a `SubgameState` amounts to, without the game inside.
+#[derive(Debug, Copy, Clone)]
+pub enum BoardStatus<P: Player> {
+    Won(P),
+    Draw,
+    Playing,
+}
+
+impl<P: Player> BoardStatus<P> {
+    pub fn is_decided(&self) -> bool {
+        match self {
+            BoardStatus::Playing => false,
+            _ => true,
+        }
+    }
+}
+
+impl<T: GenericGame<P>,P: Player> SubgameState<T,P> {
+    pub fn get_status(&self) -> BoardStatus<P> {
+        match self {
+            SubgameState::Won(player,_) => BoardStatus::Won(*player),
+            SubgameState::Draw(_) => BoardStatus::Draw,
+            SubgameState::Playing(_) => BoardStatus::Playing,
+            SubgameState::None => panic!("SubgameState::None has no status"),
+        }
+    }
+    pub fn get_game_ref(&self) -> Option<&T> {
+        match self {
+            SubgameState::Won(_,game) | SubgameState::Draw(game) | SubgameState::Playing(game) => Some(game),
+            SubgameState::None => None,
+        }
+    }
+}
+
 impl<T: GenericGame<P>,P: Player> Default for SubgameState<T,P> {
     fn default() -> Self {
         SubgameState::None
@@ -0,0 +1,234 @@
+use super::*;
+use std::cmp::{min,max};
+
+/// Writes a move the way a player would type it: the cells of the boards
+/// it goes through, then the move in the innermost board. Classic moves
+/// are a cell, quantum ones `q` and their two cells, and picking how a
+/// quantum cycle collapses is `m1` or `m2` for its first or second cell,
+/// and a dummy board, won by whoever moves in it, is played with `d`.
+/// So `4 7` is cell 7 of board 4 and `4 q0 8` a spooky mark in cells 0
+/// and 8 of board 4.
+pub fn move_notation(m: &Rc<dyn Any>) -> String {
+  if let Some(m) = m.downcast_ref::<SuperTicTacToeMove>() {
+    format!("{} {}", m.pos.get_cid(), move_notation(&m.submove))
+  } else if let Some(m) = m.downcast_ref::<TicTacToeMove>() {
+    m.pos.get_cid().to_string()
+  } else if let Some(m) = m.downcast_ref::<QuantumTicTacToeMoveEnt>() {
+    let (p1,p2) = m.get_cells();
+    format!("q{} {}", p1.get_cid(), p2.get_cid())
+  } else if let Some(m) = m.downcast_ref::<QuantumTicTacToeMoveMeas>() {
+    String::from(if m.is_first() {"m1"} else {"m2"})
+  } else if m.is::<bool>() {
+    String::from("d")
+  } else {
+    String::from("?")
+  }
+}
+
+/// Paths to the cells a move marks, through every board on the way. A
+/// move that marks no cell, like collapsing a quantum cycle, gives the
+/// path to its board.
+pub fn move_cells(m: &Rc<dyn Any>) -> Vec<Vec<Position>> {
+  if let Some(m) = m.downcast_ref::<SuperTicTacToeMove>() {
+    let mut paths = move_cells(&m.submove);
+    if paths.len() == 0 {
+      paths.push(vec!());
+    }
+    for path in paths.iter_mut() {
+      path.insert(0, m.pos);
+    }
+    paths
+  } else if let Some(m) = m.downcast_ref::<TicTacToeMove>() {
+    vec!(vec!(m.pos))
+  } else if let Some(m) = m.downcast_ref::<QuantumTicTacToeMoveEnt>() {
+    let (p1,p2) = m.get_cells();
+    vec!(vec!(p1), vec!(p2))
+  } else {
+    vec!()
+  }
+}
+
+/// Splits typed notation into words, checking each is a cell, a quantum
+/// mark or a collapse. The two cells of a quantum mark are put in order,
+/// so they can be typed either way round.
+fn notation_words(text: &str) -> Result<Vec<String>,String> {
+  let mut words: Vec<String> = text.split_whitespace().map(|w| w.to_lowercase()).collect();
+  let cell = |w: &str| w.parse::<u8>().ok().filter(|&c| c < 9);
+  let mut i = 0;
+  while i < words.len() {
+    let w = words[i].clone();
+    if w.starts_with('q') {
+      let first = cell(&w[1..]).ok_or_else(|| format!("'{}' is not a quantum mark, those go like q0 8", w))?;
+      let second = match words.get(i+1) {
+        Some(next) => cell(next).ok_or_else(|| format!("'{}' is not a cell, cells are 0 to 8", next))?,
+        None => return Err(format!("'{}' needs a second cell, like {} 8", w, w)),
+      };
+      if first == second {
+        return Err(String::from("a quantum mark needs two different cells"));
+      }
+      words[i] = format!("q{}", min(first,second));
+      words[i+1] = max(first,second).to_string();
+      i += 2;
+    } else if w.starts_with('m') {
+      if w != "m1" && w != "m2" {
+        return Err(format!("'{}' is not a collapse, those are m1 and m2", w));
+      }
+      i += 1;
+    } else {
+      if w != "d" {
+        cell(&w).ok_or_else(|| format!("'{}' is not a cell, cells are 0 to 8", w))?;
+      }
+      i += 1;
+    }
+  }
+  Ok(words)
+}
+
+/// Finds the move `text` stands for among `moves`, reading it the way
+/// `move_notation` writes it. When there's none, says which word is wrong
+/// and what could go there instead.
+pub fn find_move(moves: &[Rc<dyn Any>], text: &str) -> Result<Rc<dyn Any>,String> {
+  let words = notation_words(text)?;
+  if words.len() == 0 {
+    return Err(String::from("type a move, like 4 7 or 4 q0 8"));
+  }
+  let known: Vec<(Vec<String>,&Rc<dyn Any>)> = moves.iter().map(|m| {
+    (move_notation(m).split(' ').map(String::from).collect(),m)
+  }).collect();
+  if let Some((_,m)) = known.iter().find(|(w,_)| *w == words) {
+    return Ok(Rc::clone(m));
+  }
+  // the longest start of the move that some valid move shares
+  let mut depth = 0;
+  while depth < words.len() && known.iter().any(|(w,_)| w.len() > depth && w[..=depth] == words[..=depth]) {
+    depth += 1;
+  }
+  let sharing: Vec<&Vec<String>> = known.iter().map(|(w,_)| w).filter(|w| w.len() >= depth && w[..depth] == words[..depth]).collect();
+  let before = words[..depth].join(" ");
+  if sharing.len() == 0 {
+    return Err(String::from("there's no move to make"));
+  }
+  if sharing.iter().all(|w| w.len() == depth) {
+    return Err(format!("'{}' is a whole move already, without '{}'", before, words[depth..].join(" ")));
+  }
+  let mut options: Vec<&str> = vec!();
+  for w in sharing.iter().filter(|w| w.len() > depth) {
+    if !options.contains(&w[depth].as_str()) {
+      options.push(&w[depth]);
+    }
+  }
+  if depth == words.len() {
+    return Err(format!("'{}' is not a whole move yet, it goes on with {}", before, options.join(", ")));
+  }
+  let wrong = &words[depth];
+  let board = sharing.iter().all(|w| w.len() > depth+1);
+  let here = if depth > 0 {format!("board {}", before)} else {String::from("this board")};
+  let reason = if options.iter().all(|o| o.starts_with('m')) {
+    String::from("a cycle has to collapse first")
+  } else if wrong.starts_with('m') {
+    String::from("there's no cycle to collapse")
+  } else if options.iter().all(|o| *o == "d") {
+    format!("{} is a dummy board", here)
+  } else if wrong == "d" {
+    format!("{} is not a dummy board", here)
+  } else if options.iter().all(|o| o.starts_with('q')) && !wrong.starts_with('q') {
+    format!("{} takes quantum marks, like q0 8", here)
+  } else if wrong.starts_with('q') && !options.iter().any(|o| o.starts_with('q')) {
+    format!("{} takes single cells, not quantum marks", here)
+  } else if depth > 0 && words[depth-1].starts_with('q') {
+    format!("{} can't be marked with {}", words[depth-1], wrong)
+  } else if board {
+    format!("board {} can't be played", if depth > 0 {format!("{} {}", before, wrong)} else {wrong.clone()})
+  } else if depth > 0 {
+    format!("{} can't be played in board {}", wrong, before)
+  } else {
+    format!("{} can't be played", wrong)
+  };
+  Err(format!("{}, try {}", reason, options.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+  }
+
+  fn game(spec: &str) -> Box<dyn DynGame<P>> {
+    spec.parse::<GameSpec>().unwrap().build(P::X)
+  }
+
+  fn play(game: &mut Box<dyn DynGame<P>>, text: &str) {
+    let m = find_move(&game.get_valid_moves(), text).unwrap();
+    game.mov(m);
+  }
+
+  /// Every valid move is found again from its own notation.
+  fn round_trip(game: &Box<dyn DynGame<P>>) -> Vec<String> {
+    let moves = game.get_valid_moves();
+    assert!(moves.len() > 0);
+    moves.iter().map(|m| {
+      let text = move_notation(m);
+      let found = find_move(&moves, &text).unwrap();
+      assert!(game.is_same_move(m, &found), "{} came back as {}", text, move_notation(&found));
+      text
+    }).collect()
+  }
+
+  fn error(game: &Box<dyn DynGame<P>>, text: &str) -> String {
+    find_move(&game.get_valid_moves(), text).err().unwrap()
+  }
+
+  #[test]
+  fn every_move_type_round_trips() {
+    assert!(round_trip(&game("T")).contains(&String::from("4")));
+    assert!(round_trip(&game("S(T)")).contains(&String::from("4 7")));
+    assert!(round_trip(&game("S(S(T))")).contains(&String::from("0 4 8")));
+    assert!(round_trip(&game("S(Q)")).contains(&String::from("4 q0 8")));
+    assert!(round_trip(&game("S(D)")).contains(&String::from("4 d")));
+    let mut q = game("Q");
+    play(&mut q, "q0 1");
+    play(&mut q, "q1 2");
+    play(&mut q, "q2 0");
+    assert_eq!(round_trip(&q), vec!["m1", "m2"]);
+  }
+
+  #[test]
+  fn quantum_cells_in_either_order() {
+    let g = game("S(Q)");
+    let m = find_move(&g.get_valid_moves(), "4 Q8 0").unwrap();
+    assert_eq!(move_notation(&m), "4 q0 8");
+  }
+
+  #[test]
+  fn says_what_is_wrong() {
+    let t = game("S(T)");
+    assert!(error(&t, "").contains("type a move"));
+    assert!(error(&t, "4 9").contains("cells are 0 to 8"));
+    assert!(error(&t, "4").contains("not a whole move yet"));
+    assert!(error(&t, "4 7 1").contains("is a whole move already"));
+    assert!(error(&t, "4 q0 8").contains("takes single cells"));
+    assert!(error(&t, "4 m1").contains("no cycle to collapse"));
+    assert!(error(&t, "4 d").contains("is not a dummy board"));
+    let q = game("S(Q)");
+    assert!(error(&q, "4 7").contains("takes quantum marks"));
+    assert!(error(&q, "4 q3 3").contains("two different cells"));
+    assert!(error(&q, "4 q3").contains("needs a second cell"));
+    assert!(error(&game("S(D)"), "4 7").contains("is a dummy board"));
+    let mut c = game("Q");
+    play(&mut c, "q0 1");
+    play(&mut c, "q1 2");
+    play(&mut c, "q2 0");
+    assert!(error(&c, "q3 4").contains("has to collapse first"));
+    assert!(error(&c, "m3").contains("m1 and m2"));
+  }
+}
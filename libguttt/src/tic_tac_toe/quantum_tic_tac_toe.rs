@@ -84,9 +84,18 @@ pub struct Measurement<P: Player> {
   pub subscript: u8
 }
 
+impl<P: Player> BoardCell<P> for Option<Measurement<P>> {
+  fn owner(&self) -> Option<P> {
+    self.map(|m| m.player)
+  }
+  fn is_open(&self) -> bool {
+    self.is_none()
+  }
+}
+
 #[derive(Clone)]
 pub struct QuantumTicTacToe<P: Player> {
-  classic_marks: [Option<Measurement<P>>;9],
+  classic_marks: Board<Option<Measurement<P>>>,
   ents: Vec<Entanglement<P>>,
   player: P,
   fplayer: P,
@@ -100,11 +109,25 @@ pub struct QuantumTicTacToeMoveEnt {
   p2: Position,
 }
 
+impl QuantumTicTacToeMoveEnt {
+  /// The two cells the spooky mark goes in.
+  pub fn get_cells(&self) -> (Position,Position) {
+    (self.p1,self.p2)
+  }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct QuantumTicTacToeMoveMeas {
   first: bool
 }
 
+impl QuantumTicTacToeMoveMeas {
+  /// Whether the cycle collapses with the last mark in its first cell.
+  pub fn is_first(&self) -> bool {
+    self.first
+  }
+}
+
 impl<P: Player> GenericGame<P> for QuantumTicTacToe<P> {
   fn create(player: P, pos: Position) -> Self {
     Self::new(player)
@@ -118,15 +141,19 @@ impl<P: Player> GenericGame<P> for QuantumTicTacToe<P> {
         let mut po: Vec<Position> = vec!();
         for (pos,me,e) in meas {
           self.remove_ent(e);
-          self.classic_marks[pos.get_cid() as usize] = Some(me);
+          self.classic_marks.set(pos,Some(me));
           po.push(pos);
         }
         self.fplayer = self.player;
         self.do_meas = None;
+        let result = self.check_win();
+        let mut events = vec!(GameEvent::QuantumCollapse(vec!(), po.clone()));
+        events.extend(GameEvent::game_over(&result));
         MoveData {
           jump: Some(po),
           next_player: self.player,
-          result: self.check_win()
+          result,
+          events
         }
       } else {
         panic!("wtf?");
@@ -144,17 +171,21 @@ impl<P: Player> GenericGame<P> for QuantumTicTacToe<P> {
       if let Some(meas) = self.try_measure(ent,true) {
         self.do_meas = Some(ent);
         self.set_player(self.player.next_player());
+        let result = self.check_win();
         MoveData {
           jump: None,
           next_player: self.player,
-          result: self.check_win()
+          result,
+          events: GameEvent::game_over(&result)
         }
       } else {
         self.set_player(self.player.next_player());
+        let result = self.check_win();
         MoveData {
           jump: None,
           next_player: self.player,
-          result: self.check_win()
+          result,
+          events: GameEvent::game_over(&result)
         }
       }
     }
@@ -195,6 +226,13 @@ impl<P: Player> GenericGame<P> for QuantumTicTacToe<P> {
       } else {false}
     }
   }
+  fn is_same_move(&self, a: &Rc<dyn Any>, b: &Rc<dyn Any>) -> bool {
+    if let (Some(a),Some(b)) = (a.downcast_ref::<QuantumTicTacToeMoveEnt>(),b.downcast_ref::<QuantumTicTacToeMoveEnt>()) {
+      ((a.p1==b.p1) && (a.p2==b.p2)) || ((a.p1==b.p2) && (a.p2==b.p1))
+    } else if let (Some(a),Some(b)) = (a.downcast_ref::<QuantumTicTacToeMoveMeas>(),b.downcast_ref::<QuantumTicTacToeMoveMeas>()) {
+      a.first == b.first
+    } else {false}
+  }
   fn set_player(&mut self, player: P) {
     self.player = player;
   }
@@ -203,10 +241,31 @@ impl<P: Player> GenericGame<P> for QuantumTicTacToe<P> {
   }
 }
 
+/// Setups only place classic marks, as if they were collapsed before the
+/// first move.
+impl<P: Player> GameSetup<P> for QuantumTicTacToe<P> {
+  fn place_mark(&mut self, path: &[Position], player: P) -> Result<GameResult<P>,String> {
+    match path {
+      [pos] if self.get_classic_mark(*pos).is_none() && self.get_ents_in_cell(*pos).len() == 0 => {
+        self.classic_marks.set(*pos,Some(Measurement{player, subscript: 0}));
+        Ok(self.check_win())
+      },
+      [pos] => Err(format!("cell {} is already marked", pos.get_cid())),
+      _ => Err(String::from("quantum tic tac toe cells hold marks, not boards")),
+    }
+  }
+  fn decide(&mut self, _path: &[Position], _result: GameResult<P>) -> Result<GameResult<P>,String> {
+    Err(String::from("quantum tic tac toe cells hold marks, not boards"))
+  }
+  fn set_allowed(&mut self, _path: &[Position], _cells: Vec<Position>) -> Result<(),String> {
+    Err(String::from("only ultimate boards have allowed boards"))
+  }
+}
+
 impl<P: Player> QuantumTicTacToe<P> {
   pub fn new(player: P) -> Self {
     QuantumTicTacToe{
-      classic_marks: [None;9],
+      classic_marks: Board::new(3,3,|_,_| None),
       ents: vec!(),
       player,
       fplayer: player,
@@ -223,11 +282,20 @@ impl<P: Player> QuantumTicTacToe<P> {
     }
   }
   pub fn get_classic_mark(&self, pos: Position) -> Option<Measurement<P>> {
-    self.classic_marks[pos.get_cid() as usize]
+    *self.classic_marks.get(pos)
   }
   pub fn get_ents(&self) -> &Vec<Entanglement<P>> {
     &self.ents
   }
+  /// The spooky mark whose cycle is waiting to be collapsed.
+  pub fn get_collapse(&self) -> Option<Entanglement<P>> {
+    self.do_meas
+  }
+  /// The player who starts each round of subscripts, and the subscript of
+  /// the round so far.
+  pub fn get_round(&self) -> (P,u8) {
+    (self.fplayer,self.subscript)
+  }
   pub fn get_ents_in_cell(&self,pos:Position) -> Vec<Entanglement<P>> {
     let mut cells = vec!();
     for e in self.ents.iter() {
@@ -273,79 +341,18 @@ impl<P: Player> QuantumTicTacToe<P> {
     c
   }
 
-    fn check_win(&self) -> GameResult<P> {
+    pub(crate) fn check_win(&self) -> GameResult<P> {
         let mut rows: Vec<(P,u8)> = vec!();
-        for i in 0..3 {
-            let x = i as u8;
-            // Check diagonals
-            if x < 2 {
-                if let Some(m1) = self.get_classic_mark(Position::from_xy(1, 1))
-                {
-                    match x {
-                        0 => {
-                            if let Some(m2) = self.get_classic_mark(Position::from_xy(0, 0))
-                            {
-                                if let Some(m3) = self.get_classic_mark(Position::from_xy(2, 2))
-                                {
-                                  let b = 
-                                    m1 == m2
-                                        && m2 == m3;
-                                  if b {
-                                    rows.push((m1.player,max(m1.subscript,max(m2.subscript,m3.subscript))));
-                                  }
-                                }
-                            }
-                        }
-                        1 => {
-                            if let Some(m2) = self.get_classic_mark(Position::from_xy(2, 0))
-                            {
-                                if let Some(m3) = self.get_classic_mark(Position::from_xy(0, 2))
-                                {
-                                  let b = 
-                                    m1 == m2
-                                        && m2 == m3;
-                                  if b {
-                                    rows.push((m1.player,max(m1.subscript,max(m2.subscript,m3.subscript))));
-                                  }
-                                }
-                            }
-                        }
-                        _ => (),
-                    }
-                };
-            };
-
-            //Check vertical line
-            if let Some(m1) = self.get_classic_mark(Position::from_xy(x, 1)) {
-                if let Some(m2) = self.get_classic_mark(Position::from_xy(x, 0))
-                {
-                    if let Some(m3) = self.get_classic_mark(Position::from_xy(x, 2))
-                    {
-                                  let b = 
-                                    m1 == m2
-                                        && m2 == m3;
-                                  if b {
-                                    rows.push((m1.player,max(m1.subscript,max(m2.subscript,m3.subscript))));
-                                  }
-                    }
-                }
-            };
-
-            //Check horizontal line
-            if let Some(m1) = self.get_classic_mark(Position::from_xy(1, x)) {
-                if let Some(m2) = self.get_classic_mark(Position::from_xy(0, x))
-                {
-                    if let Some(m3) = self.get_classic_mark(Position::from_xy(2, x))
-                    {
-                                  let b = 
-                                    m1 == m2
-                                        && m2 == m3;
-                                  if b {
-                                    rows.push((m1.player,max(m1.subscript,max(m2.subscript,m3.subscript))));
-                                  }
+        for line in self.classic_marks.lines() {
+            if let Some(player) = self.classic_marks.owner_of_line(&line) {
+                let mut subscript = 0;
+                for &(x,y) in line.iter() {
+                    if let Some(m) = self.classic_marks.get_xy(x,y) {
+                        subscript = max(subscript,m.subscript);
                     }
                 }
-            };
+                rows.push((player,subscript));
+            }
         }
         if rows.len() > 0 {
           let mut irows = rows.iter();
@@ -356,11 +363,12 @@ impl<P: Player> QuantumTicTacToe<P> {
             }
           }
           let mut c = 0;
-          let mut players: Vec<String> = vec!();
+          // rows finished by the same collapse for different teams cancel out
+          let mut teams: Vec<String> = vec!();
           for row in rows.iter() {
-            if (minrow.1 == row.1) && (!players.contains(&minrow.0.get_uuid())) {
+            if (minrow.1 == row.1) && (!teams.contains(&row.0.get_team())) {
               c=c+1;
-              players.push(minrow.0.get_uuid())
+              teams.push(row.0.get_team())
             }
           }
           if c > 1 {
@@ -375,4 +383,67 @@ impl<P: Player> QuantumTicTacToe<P> {
           GameResult::Incomplete
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// X and O play alone, T plays with X.
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O, T}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::T, P::T => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+    fn get_team(&self) -> String {
+      String::from(if *self == P::O {"O"} else {"X"})
+    }
+  }
+
+  /// A board with the classic marks `marks`, as (cell, player, subscript).
+  fn board(marks: &[(u8,P,u8)]) -> QuantumTicTacToe<P> {
+    let mut game = QuantumTicTacToe::create(P::X, Position::from_cid(0));
+    for &(cid,player,subscript) in marks.iter() {
+      game.classic_marks.set(Position::from_cid(cid), Some(Measurement{player, subscript}));
+    }
+    game
+  }
+
+  fn winner(result: GameResult<P>) -> Option<P> {
+    match result {
+      GameResult::Won(p) => Some(p),
+      _ => None,
+    }
+  }
+
+  #[test]
+  fn earlier_row_wins() {
+    let game = board(&[(0,P::X,1),(1,P::X,2),(2,P::X,3),(6,P::O,1),(7,P::O,2),(8,P::O,4)]);
+    assert_eq!(winner(game.check_win()), Some(P::X));
+  }
+
+  #[test]
+  fn simultaneous_rows_of_rivals_draw() {
+    let game = board(&[(0,P::X,1),(1,P::X,2),(2,P::X,3),(6,P::O,1),(7,P::O,2),(8,P::O,3)]);
+    match game.check_win() {
+      GameResult::Draw => (),
+      r => panic!("expected a draw, got {:?}", winner(r)),
+    }
+  }
+
+  #[test]
+  fn simultaneous_rows_of_teammates_win() {
+    let game = board(&[(0,P::X,1),(1,P::X,2),(2,P::X,3),(6,P::T,1),(7,P::T,2),(8,P::T,3)]);
+    assert!(game.check_win().is_won_by(P::X));
+  }
+
+  #[test]
+  fn mixed_team_row_wins() {
+    let game = board(&[(0,P::X,1),(4,P::T,1),(8,P::X,2)]);
+    assert!(game.check_win().is_won_by(P::T));
+  }
+}
@@ -0,0 +1,248 @@
+use super::*;
+use std::fmt;
+
+/// Direct edits to a game's state, for starting from a position instead of
+/// from an empty board. Paths lead from this game down through subboards.
+pub trait GameSetup<P: Player> {
+  /// Puts a mark of `player` in the leaf cell at the end of `path`. Returns
+  /// what the game amounts to afterwards.
+  fn place_mark(&mut self, path: &[Position], player: P) -> Result<GameResult<P>,String>;
+  /// Declares the board at the end of `path` won or drawn.
+  fn decide(&mut self, path: &[Position], result: GameResult<P>) -> Result<GameResult<P>,String>;
+  /// Sets the boards the next move may go to in the board at `path`.
+  fn set_allowed(&mut self, path: &[Position], cells: Vec<Position>) -> Result<(),String>;
+}
+
+impl<P: Player + 'static> GameSetup<P> for Box<dyn DynGame<P>> {
+  fn place_mark(&mut self, path: &[Position], player: P) -> Result<GameResult<P>,String> {
+    (**self).place_mark(path, player)
+  }
+  fn decide(&mut self, path: &[Position], result: GameResult<P>) -> Result<GameResult<P>,String> {
+    (**self).decide(path, result)
+  }
+  fn set_allowed(&mut self, path: &[Position], cells: Vec<Position>) -> Result<(),String> {
+    (**self).set_allowed(path, cells)
+  }
+}
+
+#[derive(Clone, Debug)]
+enum SetupLine {
+  Mark(Vec<Position>, String),
+  Won(Vec<Position>, String),
+  Drawn(Vec<Position>),
+  Allowed(Vec<Position>, Vec<Position>),
+  ToMove(String),
+  /// A move played from there, in `move_notation`.
+  Move(String),
+}
+
+/// A starting position, written one directive per line:
+///
+/// ```text
+/// # comments start with a hash
+/// game S(T)        # what to play, if not given elsewhere
+/// seed 42          # what the bots were seeded with, if not given elsewhere
+/// won 4 X          # X already has the centre board
+/// drawn 0          # the top left board is a draw
+/// mark 2 4 O       # O has the centre cell of the top right board
+/// allowed 1 2 5    # the first move goes to one of these boards
+/// allowed 3 / 0 8  # the same, inside board 3
+/// to-move O        # O moves first
+/// move 2 4         # then the game goes on with these moves
+/// ```
+///
+/// Cells are numbered 0 to 8, row by row from the top left, and several of
+/// them in a row are a path into nested boards. Lines can also be
+/// separated with `;`. Moves come after everything else, since they're
+/// played as they are read.
+#[derive(Clone, Debug, Default)]
+pub struct Setup {
+  pub game: Option<GameSpec>,
+  pub seed: Option<u64>,
+  lines: Vec<SetupLine>,
+}
+
+fn parse_cells(words: &[&str]) -> Result<Vec<Position>,String> {
+  let mut cells = vec!();
+  for w in words.iter() {
+    match w.parse::<u8>() {
+      Ok(cid) if cid < 9 => cells.push(Position::from_cid(cid)),
+      _ => return Err(format!("'{}' is not a cell, cells are 0 to 8", w)),
+    }
+  }
+  Ok(cells)
+}
+
+impl Setup {
+  pub fn parse(text: &str) -> Result<Setup,String> {
+    let mut setup = Setup::default();
+    for (n,line) in text.split(|c| c == '\n' || c == ';').enumerate() {
+      let line = line.splitn(2, '#').next().unwrap_or("");
+      let words: Vec<&str> = line.split_whitespace().collect();
+      if words.len() == 0 {
+        continue;
+      }
+      let err = |e: String| format!("setup line {}: {}", n+1, e);
+      let args = &words[1..];
+      let parsed = match words[0] {
+        "game" => {
+          setup.game = Some(args.join("").parse().map_err(err)?);
+          continue;
+        },
+        "seed" if args.len() == 1 => {
+          setup.seed = Some(args[0].parse().map_err(|_| err(format!("'{}' is not a seed", args[0])))?);
+          continue;
+        },
+        "seed" => return Err(err(String::from("seed needs one number"))),
+        "mark" | "won" if args.len() < 2 => return Err(err(format!("{} needs cells and a player", words[0]))),
+        "mark" => SetupLine::Mark(parse_cells(&args[..args.len()-1]).map_err(err)?, String::from(args[args.len()-1])),
+        "won" => SetupLine::Won(parse_cells(&args[..args.len()-1]).map_err(err)?, String::from(args[args.len()-1])),
+        "drawn" if args.len() == 0 => return Err(err(String::from("drawn needs cells"))),
+        "drawn" => SetupLine::Drawn(parse_cells(args).map_err(err)?),
+        "allowed" => {
+          match args.iter().position(|&w| w == "/") {
+            Some(i) => SetupLine::Allowed(parse_cells(&args[..i]).map_err(err)?, parse_cells(&args[i+1..]).map_err(err)?),
+            None => SetupLine::Allowed(vec!(), parse_cells(args).map_err(err)?),
+          }
+        },
+        "to-move" if args.len() == 1 => SetupLine::ToMove(String::from(args[0])),
+        "to-move" => return Err(err(String::from("to-move needs one player"))),
+        "move" if args.len() == 0 => return Err(err(String::from("move needs a move"))),
+        "move" => SetupLine::Move(args.join(" ")),
+        w => return Err(err(format!("unknown directive '{}'", w))),
+      };
+      setup.lines.push(parsed);
+    }
+    Ok(setup)
+  }
+
+  /// Adds a move to play after the rest of the setup.
+  pub fn push_move(&mut self, notation: String) {
+    self.lines.push(SetupLine::Move(notation));
+  }
+
+  /// Applies the setup to a fresh game, finding players by name with
+  /// `find_player`. Returns what the game amounts to afterwards.
+  pub fn apply<P, G, F>(&self, game: &mut G, find_player: F) -> Result<GameResult<P>,String> where
+    P: Player,
+    G: GenericGame<P> + GameSetup<P>,
+    F: Fn(&str) -> Option<P>
+  {
+    let player = |name: &String| find_player(name).ok_or_else(|| format!("unknown player '{}'", name));
+    let mut result = GameResult::Incomplete;
+    for line in self.lines.iter() {
+      if let GameResult::Incomplete = result {} else {
+        return Err(String::from("the game is already over before the setup ends"));
+      }
+      match line {
+        SetupLine::Mark(path,name) => {result = game.place_mark(path, player(name)?)?;},
+        SetupLine::Won(path,name) => {result = game.decide(path, GameResult::Won(player(name)?))?;},
+        SetupLine::Drawn(path) => {result = game.decide(path, GameResult::Draw)?;},
+        SetupLine::Allowed(path,cells) => game.set_allowed(path, cells.clone())?,
+        SetupLine::ToMove(name) => game.set_player(player(name)?),
+        SetupLine::Move(text) => {
+          let m = find_move(&game.get_valid_moves(), text).map_err(|e| format!("move {}: {}", text, e))?;
+          result = game.mov(m).result;
+        },
+      }
+    }
+    Ok(result)
+  }
+}
+
+fn write_cells(cells: &[Position]) -> String {
+  cells.iter().map(|pos| pos.get_cid().to_string()).collect::<Vec<String>>().join(" ")
+}
+
+/// Writes the setup back in the form `parse` reads.
+impl fmt::Display for Setup {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if let Some(game) = &self.game {
+      writeln!(f, "game {}", game)?;
+    }
+    if let Some(seed) = self.seed {
+      writeln!(f, "seed {}", seed)?;
+    }
+    for line in self.lines.iter() {
+      match line {
+        SetupLine::Mark(path,name) => writeln!(f, "mark {} {}", write_cells(path), name)?,
+        SetupLine::Won(path,name) => writeln!(f, "won {} {}", write_cells(path), name)?,
+        SetupLine::Drawn(path) => writeln!(f, "drawn {}", write_cells(path))?,
+        SetupLine::Allowed(path,cells) if path.len() == 0 => writeln!(f, "allowed {}", write_cells(cells))?,
+        SetupLine::Allowed(path,cells) => writeln!(f, "allowed {} / {}", write_cells(path), write_cells(cells))?,
+        SetupLine::ToMove(name) => writeln!(f, "to-move {}", name)?,
+        SetupLine::Move(text) => writeln!(f, "move {}", text)?,
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+  }
+
+  fn find_player(name: &str) -> Option<P> {
+    match name {"X" => Some(P::X), "O" => Some(P::O), _ => None}
+  }
+
+  /// Plays `setup` on its own game, returning the moves left afterwards.
+  fn moves_after(setup: &Setup) -> Result<Vec<String>,String> {
+    let mut game = setup.game.as_ref().unwrap().build(P::X);
+    setup.apply(&mut game, find_player)?;
+    Ok(game.get_valid_moves().iter().map(move_notation).collect())
+  }
+
+  const EVERYTHING: &str = "game S(T)\nseed 42\nwon 4 X\ndrawn 0\nmark 2 4 O\nallowed 1 2 5\nallowed 3 / 0 8\nto-move O\nmove 2 0\n";
+
+  #[test]
+  fn reads_back_what_it_writes() {
+    let setup = Setup::parse(EVERYTHING).unwrap();
+    assert_eq!(setup.to_string(), EVERYTHING);
+    let messy = Setup::parse("# a comment\ngame  S( T )  # trailing\nseed 42;won 4 X; drawn 0\nmark 2 4 O\nallowed 1 2 5\n\nallowed 3 / 0 8\nto-move O\nmove 2 0").unwrap();
+    assert_eq!(messy.to_string(), EVERYTHING);
+    assert_eq!(Setup::default().to_string(), "");
+  }
+
+  #[test]
+  fn moves_and_directives_apply() {
+    let mut setup = Setup::parse("game S(T)\nwon 4 X\ndrawn 0\nmark 2 4 O\nallowed 1 2 5\nto-move O\nmove 2 0").unwrap();
+    // O took cell 0 of board 2, so X goes to board 0, which is drawn
+    let moves = moves_after(&setup).unwrap();
+    assert!(moves.iter().all(|m| !m.starts_with("0 ") && !m.starts_with("4 ")));
+    assert!(moves.contains(&String::from("1 4")));
+    assert!(!moves.contains(&String::from("2 4")));
+    setup.push_move(String::from("1 2"));
+    assert_eq!(Setup::parse(&setup.to_string()).unwrap().to_string(), setup.to_string());
+    assert!(moves_after(&setup).unwrap().iter().all(|m| m.starts_with("2 ")));
+  }
+
+  #[test]
+  fn says_what_is_wrong() {
+    let err = |text: &str| Setup::parse(text).err().unwrap();
+    assert_eq!(err("game S(T)\nfoo 1"), "setup line 2: unknown directive 'foo'");
+    assert_eq!(err("mark 9 X"), "setup line 1: '9' is not a cell, cells are 0 to 8");
+    assert_eq!(err("mark X"), "setup line 1: mark needs cells and a player");
+    assert_eq!(err("drawn"), "setup line 1: drawn needs cells");
+    assert_eq!(err("to-move X O"), "setup line 1: to-move needs one player");
+    assert_eq!(err("seed many"), "setup line 1: 'many' is not a seed");
+    assert_eq!(err("move"), "setup line 1: move needs a move");
+    assert_eq!(err("game S(T"), "setup line 1: expected ')' at 3, found end of game spec");
+    let apply = |text: &str| moves_after(&Setup::parse(text).unwrap()).err().unwrap();
+    assert_eq!(apply("game S(T)\nmark 4 4 Z"), "unknown player 'Z'");
+    assert!(apply("game S(T)\nmove 4 9").starts_with("move 4 9: "));
+    assert_eq!(apply("game S(T)\nwon 0 X\nwon 1 X\nwon 2 X\nmove 4 4"), "the game is already over before the setup ends");
+  }
+}
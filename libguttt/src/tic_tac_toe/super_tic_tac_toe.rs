@@ -11,9 +11,11 @@ pub struct SuperTicTacToeMove {
 
 #[derive(Clone)]
 pub struct SuperTicTacToe<T: GenericGame<P>,P: Player> {
-    grid: [SubgameState<T,P>; 9],
+    grid: Board<SubgameState<T,P>>,
     player: P,
     jump: Vec<Position>,
+    last: Option<(Position,P)>,
+    move_count: usize,
 }
 
 pub trait SubgameGen<T: GenericGame<P>,P:Player>: Fn(Position, P) -> T {}
@@ -24,15 +26,15 @@ impl<T,U: GenericGame<P>,P: Player> SubgameGen<U,P> for T where
 
 impl<T: GenericGame<P>, P: Player> SuperTicTacToe<T,P> {
     pub fn new<U: SubgameGen<T,P>>(player: P, new_board: U) -> Self {
-        let mut grid: [SubgameState<T,P>; 9] = Default::default();
-        for i in 0..9 {
-            grid[i] =
-                SubgameState::Playing(new_board(Position::from_cid(i as u8), player));
-        }
+        let grid = Board::new(3, 3, |x, y| {
+            SubgameState::Playing(new_board(Position::from_xy(x, y), player))
+        });
         SuperTicTacToe {
             grid,
             player: player,
             jump: Self::alljump(),
+            last: None,
+            move_count: 0,
         }
     }
 
@@ -45,25 +47,53 @@ impl<T: GenericGame<P>, P: Player> SuperTicTacToe<T,P> {
     }
 
     pub fn get_cell_ref(&self, pos: Position) -> &SubgameState<T,P> {
-        &self.grid[pos.get_cid() as usize]
+        self.grid.get(pos)
     }
     fn get_cell_mut(&mut self, pos: Position) -> &mut SubgameState<T,P> {
-        &mut self.grid[pos.get_cid() as usize]
+        self.grid.get_mut(pos)
     }
     fn set_cell(&mut self, pos: Position, s: SubgameState<T,P>) {
-        self.grid[pos.get_cid() as usize] = s;
+        self.grid.set(pos, s);
     }
     fn get_cell(&mut self, pos: Position) -> SubgameState<T,P> {
-        std::mem::replace(&mut self.grid[pos.get_cid() as usize],SubgameState::None)
+        self.grid.set(pos, SubgameState::None)
+    }
+    pub fn get_board(&self) -> &Board<SubgameState<T,P>> {
+        &self.grid
     }
 
     fn set_jump(&mut self, ojump: &Option<Vec<Position>>, from: Position) {
         self.jump = self.sanitize_jump(ojump, from);
     }
-    fn get_jump(&self) -> &Vec<Position> {
+    /// Boards the next move may be played in.
+    pub fn get_jump(&self) -> &Vec<Position> {
         &self.jump
     }
 
+    /// Board the last move was played in, and who played it.
+    pub fn get_last_move(&self) -> Option<(Position,P)> {
+        self.last
+    }
+
+    /// Moves played in this board so far.
+    pub fn get_move_count(&self) -> usize {
+        self.move_count
+    }
+
+    pub fn get_board_status(&self, pos: Position) -> BoardStatus<P> {
+        self.get_cell_ref(pos).get_status()
+    }
+
+    /// Status of every board, row by row from the top left.
+    pub fn get_board_statuses(&self) -> Vec<BoardStatus<P>> {
+        self.grid.cells().map(|cell| cell.get_status()).collect()
+    }
+
+    /// Whether the board at `pos` has been won or drawn.
+    pub fn is_decided(&self, pos: Position) -> bool {
+        self.get_board_status(pos).is_decided()
+    }
+
     fn sanitize_jump_raw(&self, ojump: &Option<Vec<Position>>, from: Position) -> Vec<Position> {
         let mut gvm = [false; 9];
         let mut res: Vec<Position> = Vec::with_capacity(9);
@@ -110,99 +140,64 @@ impl<T: GenericGame<P>, P: Player> SuperTicTacToe<T,P> {
     }
 
     fn check_win(&self) -> GameResult<P> {
-        for i in 0..3 {
-            let x = i as u8;
-            // Check diagonals
-            if x < 2 {
-                if let SubgameState::<T,P>::Won(player,_) = Self::get_cell_ref(self, Position::from_xy(1, 1))
-                {
-                    if match x {
-                        0 => {
-                            if let SubgameState::<T,P>::Won(player2,_) =
-                                Self::get_cell_ref(self, Position::from_xy(0, 0))
-                            {
-                                if let SubgameState::<T,P>::Won(player3,_) =
-                                    Self::get_cell_ref(self, Position::from_xy(2, 2))
-                                {
-                                    player.get_uuid() == player2.get_uuid()
-                                        && player2.get_uuid() == player3.get_uuid()
-                                } else {
-                                    false
-                                }
-                            } else {
-                                false
-                            }
-                        }
-                        1 => {
-                            if let SubgameState::<T,P>::Won(player2,_) =
-                                Self::get_cell_ref(self, Position::from_xy(2, 0))
-                            {
-                                if let SubgameState::<T,P>::Won(player3,_) =
-                                    Self::get_cell_ref(self, Position::from_xy(0, 2))
-                                {
-                                    player.get_uuid() == player2.get_uuid()
-                                        && player2.get_uuid() == player3.get_uuid()
-                                } else {
-                                    false
-                                }
-                            } else {
-                                false
-                            }
-                        }
-                        _ => false,
-                    } {
-                        return GameResult::<P>::Won(*player);
-                    };
-                };
-            };
+        self.grid.check_win()
+    }
 
-            //Check vertical line
-            if let SubgameState::<T,P>::Won(player,_) = Self::get_cell_ref(self, Position::from_xy(x, 1)) {
-                if if let SubgameState::<T,P>::Won(player2,_) =
-                    Self::get_cell_ref(self, Position::from_xy(x, 0))
-                {
-                    if let SubgameState::<T,P>::Won(player3,_) =
-                        Self::get_cell_ref(self, Position::from_xy(x, 2))
-                    {
-                        player.get_uuid() == player2.get_uuid()
-                            && player2.get_uuid() == player3.get_uuid()
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                } {
-                    return GameResult::<P>::Won(*player);
-                };
-            };
+    /// Turns the board at `pos` into a decided one if `result` says so,
+    /// and keeps the jump off decided boards.
+    fn settle(&mut self, pos: Position, result: GameResult<P>) {
+        let state = self.get_cell(pos);
+        self.set_cell(pos, match (state, result) {
+            (SubgameState::Playing(game), GameResult::Won(player)) => SubgameState::Won(player, game),
+            (SubgameState::Playing(game), GameResult::Draw) => SubgameState::Draw(game),
+            (state, _) => state,
+        });
+        self.jump = self.sanitize_jump(&Some(self.jump.clone()), pos);
+    }
 
-            //Check horizontal line
-            if let SubgameState::<T,P>::Won(player,_) = Self::get_cell_ref(self, Position::from_xy(1, x)) {
-                if if let SubgameState::<T,P>::Won(player2,_) =
-                    Self::get_cell_ref(self, Position::from_xy(0, x))
-                {
-                    if let SubgameState::<T,P>::Won(player3,_) =
-                        Self::get_cell_ref(self, Position::from_xy(2, x))
-                    {
-                        player.get_uuid() == player2.get_uuid()
-                            && player2.get_uuid() == player3.get_uuid()
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                } {
-                    return GameResult::<P>::Won(*player);
-                };
-            };
+    fn get_playing_mut(&mut self, pos: Position) -> Result<&mut T,String> {
+        match self.get_cell_mut(pos) {
+            SubgameState::Playing(game) => Ok(game),
+            _ => Err(format!("board {} is already decided", pos.get_cid())),
         }
-        let mut result = GameResult::<P>::Draw;
-        for i in 0..9 {
-            if let SubgameState::<T,P>::Playing(_) = Self::get_cell_ref(self, Position::from_cid(i)) {
-                result = GameResult::<P>::Incomplete; break;
-            };
+    }
+}
+
+impl<T: GenericGame<P> + GameSetup<P>, P: Player> GameSetup<P> for SuperTicTacToe<T,P> {
+    fn place_mark(&mut self, path: &[Position], player: P) -> Result<GameResult<P>,String> {
+        let (pos, rest) = path.split_first()
+            .ok_or_else(|| String::from("marks go in cells, not on a whole ultimate board"))?;
+        if rest.len() == 0 {
+            return Err(format!("cell {} holds a board, use won to give it to someone", pos.get_cid()));
+        }
+        let result = self.get_playing_mut(*pos)?.place_mark(rest, player)?;
+        self.settle(*pos, result);
+        Ok(self.check_win())
+    }
+    fn decide(&mut self, path: &[Position], result: GameResult<P>) -> Result<GameResult<P>,String> {
+        let (pos, rest) = path.split_first()
+            .ok_or_else(|| String::from("can't decide the whole game in a setup"))?;
+        let result = if rest.len() == 0 {
+            self.get_playing_mut(*pos)?;
+            result
+        } else {
+            self.get_playing_mut(*pos)?.decide(rest, result)?
+        };
+        self.settle(*pos, result);
+        Ok(self.check_win())
+    }
+    fn set_allowed(&mut self, path: &[Position], cells: Vec<Position>) -> Result<(),String> {
+        match path.split_first() {
+            Some((pos, rest)) => self.get_playing_mut(*pos)?.set_allowed(rest, cells),
+            None => {
+                let jump = self.sanitize_jump_raw(&Some(cells), Position::default());
+                if jump.len() == 0 {
+                    return Err(String::from("none of the allowed boards is still being played"));
+                }
+                self.jump = jump;
+                Ok(())
+            }
         }
-        result
     }
 }
 
@@ -233,6 +228,18 @@ impl<T: GenericGame<P>, P: Player> GenericGame<P> for SuperTicTacToe<T,P> {
           }
         } else {false}
     }
+    fn is_same_move(&self, a: &Rc<dyn Any>, b: &Rc<dyn Any>) -> bool {
+        match (a.downcast_ref::<SuperTicTacToeMove>(), b.downcast_ref::<SuperTicTacToeMove>()) {
+            (Some(a), Some(b)) if a.pos == b.pos => {
+                if let SubgameState::<T,P>::Playing(game) = self.get_cell_ref(a.pos) {
+                    game.is_same_move(&a.submove, &b.submove)
+                } else {
+                    false
+                }
+            },
+            _ => false,
+        }
+    }
     fn get_valid_moves(&self) -> Vec<Rc<dyn Any>> {
         let mut vcells: Vec<Position> = Vec::with_capacity(9);
         for i in 0..9 {
@@ -264,13 +271,18 @@ impl<T: GenericGame<P>, P: Player> GenericGame<P> for SuperTicTacToe<T,P> {
 
         assert!(self.is_valid_move(Rc::clone(&m)), "invalid move");
         let player = self.player;
+        let old_jump = self.jump.clone();
 
         if let SubgameState::Playing(mut game) = self.get_cell(mo.pos) {
             game.set_player(player);
             let mdata = game.mov(Rc::clone(&mo.submove));
             self.set_player(mdata.next_player);
+            self.last = Some((mo.pos,player));
+            self.move_count += 1;
             let result;
-            let md = match mdata.result {
+            let mut events: Vec<GameEvent<P>> =
+                mdata.events.into_iter().map(|e| e.nest(mo.pos)).collect();
+            let mut md = match mdata.result {
                 GameResult::Won(player) => {
                     self.set_cell(mo.pos, SubgameState::Won(player,game));
                     result = self.check_win();
@@ -279,6 +291,7 @@ impl<T: GenericGame<P>, P: Player> GenericGame<P> for SuperTicTacToe<T,P> {
                         jump: Some(vec![mo.pos]),
                         next_player: mdata.next_player,
                         result,
+                        events: vec!(),
                     }
                 }
                 GameResult::Draw => {
@@ -289,6 +302,7 @@ impl<T: GenericGame<P>, P: Player> GenericGame<P> for SuperTicTacToe<T,P> {
                         jump: None,
                         next_player: mdata.next_player,
                         result,
+                        events: vec!(),
                     }
                 }
                 GameResult::Incomplete => {
@@ -299,13 +313,20 @@ impl<T: GenericGame<P>, P: Player> GenericGame<P> for SuperTicTacToe<T,P> {
                         jump: None,
                         next_player: mdata.next_player,
                         result,
+                        events: vec!(),
                     }
                 },
             };
             match result {
-              GameResult::Incomplete => (),
+              GameResult::Incomplete => {
+                if self.jump != old_jump {
+                  events.push(GameEvent::JumpChanged(vec!(), self.jump.clone()));
+                }
+              },
               _ => {self.set_jump(&None,mo.pos)}
             };
+            events.extend(GameEvent::game_over(&result));
+            md.events = events;
             md
         } else {
             panic!("wtf?");
@@ -0,0 +1,116 @@
+use crate::tic_tac_toe::*;
+use crate::terminal_render::*;
+use crate::rendering::Highlight;
+use crate::roster::RosterPlayer;
+
+/// Colours of the best few moves, best first.
+pub const RANKS: [ColorEnum; 3] = [ColorEnum::Green, ColorEnum::Yellow, ColorEnum::Cyan];
+
+/// One move of a bot's last search, written out so it can leave the
+/// thread that searched.
+#[derive(Clone, Debug)]
+pub struct AnalysisLine {
+  pub notation: String,
+  pub cells: Vec<Vec<Position>>,
+  pub visits: u64,
+  pub wins: f64,
+  pub draws: f64,
+  pub losses: f64,
+  /// Loss rates to each of the analysis' `opponents`.
+  pub losses_to: Vec<f64>,
+  pub score: f64,
+}
+
+/// What a bot thought about the position it last moved in, most visited
+/// moves first.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+  pub player: RosterPlayer,
+  /// Names of the teams playing against `player`.
+  pub opponents: Vec<String>,
+  pub lines: Vec<AnalysisLine>,
+}
+
+impl Analysis {
+  pub fn new(player: RosterPlayer, stats: &[MoveStats]) -> Self {
+    let opponents = player.opponents();
+    let mut lines: Vec<AnalysisLine> = stats.iter().map(|s| AnalysisLine{
+      notation: move_notation(&s.mov),
+      cells: move_cells(&s.mov),
+      visits: s.visits,
+      wins: s.win_rate(),
+      draws: s.draw_rate(),
+      losses: s.loss_rate(),
+      losses_to: opponents.iter().map(|o| s.loss_rate_to(&o.get_team())).collect(),
+      score: s.score,
+    }).collect();
+    lines.sort_by(|a,b| b.visits.cmp(&a.visits).then(b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)));
+    Analysis{player, opponents: opponents.iter().map(|o| o.team_name()).collect(), lines}
+  }
+
+  pub fn playouts(&self) -> u64 {
+    self.lines.iter().map(|l| l.visits).sum()
+  }
+
+  /// The cells of the best moves, in their rank colours.
+  pub fn highlight(&self) -> Highlight {
+    let mut cells = vec!();
+    for (line,color) in self.lines.iter().filter(|l| l.visits > 0).zip(RANKS.iter()) {
+      for path in line.cells.iter() {
+        cells.push((path.clone(),*color));
+      }
+    }
+    Highlight(cells)
+  }
+
+  /// Draws the top `rows` moves as a table in the top right corner. With
+  /// more than one team against the player, L% is split up by who won.
+  pub fn render(&self, term: &mut TerminalCanvas, rows: usize) {
+    let split = self.opponents.len() > 1;
+    let heads: Vec<String> = if split {
+      self.opponents.iter().map(|o| format!("{}%", o)).collect()
+    } else {
+      vec!()
+    };
+    let column = |head: &String, cell: String| format!(" {:>1$}", cell, head.len().max(4));
+    let mut head = format!("{:<10} {:>7} {:>4} {:>4} {:>4}", "move", "visits", "W%", "D%", "L%");
+    for h in heads.iter() {
+      head.push_str(&column(h, h.clone()));
+    }
+    let mut text = vec!(
+      format!("{}'s last search: {} playouts", self.player.name(), self.playouts()),
+      format!("{} {:>5}", head, "score"),
+    );
+    for line in self.lines.iter().take(rows) {
+      if line.visits == 0 {
+        let mut row = format!("{:<10} {:>7} {:>4} {:>4} {:>4}", line.notation, 0, "-", "-", "-");
+        for h in heads.iter() {
+          row.push_str(&column(h, String::from("-")));
+        }
+        text.push(format!("{} {:>5}", row, "-"));
+        continue;
+      }
+      let mut row = format!("{:<10} {:>7} {:>4.0} {:>4.0} {:>4.0}",
+        line.notation, line.visits, line.wins*100.0, line.draws*100.0, line.losses*100.0);
+      for (h,rate) in heads.iter().zip(line.losses_to.iter()) {
+        row.push_str(&column(h, format!("{:.0}", rate*100.0)));
+      }
+      text.push(format!("{} {:>5.2}", row, line.score));
+    }
+    let width = text.iter().map(|t| t.chars().count()).max().unwrap_or(0);
+    let (w,_) = term.get_size();
+    if width+4 > w {
+      return;
+    }
+    let x = (w-width-3) as isize;
+    let (fg,bg) = term.get_color();
+    for (i,t) in text.iter().enumerate() {
+      term.set_color(ColorEnum::White,ColorEnum::Black);
+      if i >= 2 && i-2 < RANKS.len() && self.lines[i-2].visits > 0 {
+        term.set_fg(RANKS[i-2]);
+      }
+      term.set(x,(i+2) as isize,format!("{:1$}",t,width).as_str());
+    }
+    term.set_color(fg,bg);
+  }
+}
@@ -0,0 +1,29 @@
+use crate::tic_tac_toe::*;
+use crate::options::Options;
+use crate::new_game;
+use std::fs;
+use std::io::{Write,stderr};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// Builds an opening book for `opts.game`, letting the bot of whoever is
+/// to move search each position from scratch, and writes it to `path`.
+pub fn make(opts: &Options, path: &str) -> Result<(),String> {
+  if opts.setup.is_some() {
+    return Err(String::from("opening books start from the usual position, not a setup"));
+  }
+  let game = new_game(&opts.game, opts.roster, &None)?;
+  let mut book = OpeningBook::new(opts.game.to_string(), opts.roster.names(), opts.roster.team_names());
+  let seed = opts.seed.unwrap_or_else(rand::random);
+  eprintln!("seed {}", seed);
+  let mut rng = StdRng::seed_from_u64(seed);
+  book.generate(&game, opts.book_depth, |game| {
+    let config = opts.bots[game.get_player().get_id()].clone();
+    Bot::new(config, opts.threads).choose(game, None, &mut rng)
+  }, |len| {
+    eprint!("\r{} positions", len);
+    stderr().flush().ok();
+  });
+  eprintln!();
+  fs::write(path, book.to_string()).map_err(|e| format!("can't write {}: {}", path, e))
+}
@@ -2,144 +2,209 @@ pub extern crate libguttt;
 pub use libguttt as tic_tac_toe;
 
 mod terminal_render;
-mod pl_xo;
+mod roster;
 mod rendering;
+mod options;
+mod tournament;
+mod book;
+mod analysis;
+mod seat;
+mod terminal_input;
 
 use tic_tac_toe::*;
 use terminal_render::*;
-use pl_xo::pl_XO;
-use pl_XO::*;
+use roster::{Roster,RosterPlayer};
+use terminal_input::{Control,InputHandle};
+use options::{Options,USAGE};
 
-use std::ops::Range;
 use std::rc::Rc;
 use std::sync::{Arc,Mutex};
 use std::mem::drop;
 use std::cmp::min;
 use std::thread;
 use std::time::{Instant,Duration};
-use std::io::{Write, stdout, stdin};
+use std::io::{Write, stdout};
 use std::any::Any;
-use std::fmt::Debug;
 use std::cmp::max;
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel,RecvTimeoutError};
+use std::sync::atomic::{AtomicUsize,Ordering};
 use std::mem::MaybeUninit;
 
 extern crate termion;
 extern crate unicode_segmentation;
 extern crate rand;
-use rand::seq::IteratorRandom;
-use rand::Rng;
-use termion::color;
-use termion::clear;
+use rand::{Rng,SeedableRng};
+use rand::rngs::StdRng;
 use termion::cursor;
-use termion::screen::{ToAlternateScreen,ToMainScreen,AlternateScreen};
+use termion::screen::{ToMainScreen,AlternateScreen};
 use termion::input::TermRead;
-use termion::event::Key;
+use termion::event::{Event,Key,MouseEvent,MouseButton};
+use termion::input::MouseTerminal;
 use termion::raw::IntoRawMode;
-struct MoveScore(f64,Rc<dyn Any>);
+fn good_size(mw: usize,mh: usize) -> (usize,usize) {
+  loop {
+    let (w,h) = termion::terminal_size().expect("error!");
+    if ((w as usize) >= mw) && ((h as usize) >= mh) {
+      return (w as usize,h as usize);
+    }
+  }
+}
 
-#[derive(Clone)]
-enum BotMode {
-  Time(u64,Option<Box<BotMode>>),
-  Count(u64,Option<Box<BotMode>>)
+fn describe_path(path: &[Position]) -> String {
+  let cids: Vec<String> = path.iter().map(|pos| pos.get_cid().to_string()).collect();
+  format!("board {}",cids.join(" "))
 }
 
-fn best_move<T: GenericGame<P> + Clone,P: Player, R: rand::Rng>(game: T, player: P, rng: &mut R, dat: BotMode) -> Option<Rc<dyn Any>> {
-  let vmoves = game.get_valid_moves();
-  if vmoves.len() == 0 {
-    return None;
-  }
-  let mut move_score = Vec::<MoveScore>::new();
-  let rmc = vmoves.len();
-  let mut maxtime: Option<Duration> = None;
-  let mut maxcount: Option<u64> = None;
-  let bot: Option<BotMode>;
-  match dat.clone() {
-    BotMode::Time(n,b) => {bot = if let Some(rb) = b {Some(*rb)} else {None};maxtime = Some(Duration::from_millis(n/(rmc as u64)));},
-    BotMode::Count(n,b) => {bot = if let Some(rb) = b {Some(*rb)} else {None};maxcount = Some(n);}
-  }
-  for m in vmoves.iter() {
-    let mut score: f64 = 0.0;
-    let st = Instant::now();
-    let mut count: u64 = 0;
-    loop {
-      let mut gres = GameResult::<P>::Incomplete;
-      let mut game = game.clone();
-      game.mov(Rc::clone(m));
-      let mut movesn: f64 = 0.0;
-      loop {
-        let rmove: Option<Rc<dyn Any>>;
-        match bot.clone() {
-          Some(botmode) => {
-            rmove = best_move(game.clone(),player,rng,botmode);
-          },
-          None => {
-            let moves = game.get_valid_moves();
-            rmove = if let Some(rm) = moves.iter().choose(rng) {
-              Some(Rc::clone(rm))
-            } else {
-              None
-            };
-          }
-        }
-        if let Some(rm) = rmove {
-          movesn += 1.0;
-          gres = game.mov(Rc::clone(&rm)).result;
-        } else {
-          break;
-        }
-      }
-      match gres {
-        GameResult::Won(pl) => {
-          if player.get_uuid() == pl.get_uuid() {
-            score = score + 1.0;
-          } else {
-            score = score - 2.0;
-          }
-        },
-        GameResult::Draw => {score -= 1.0},
-        Incomplete => ()
-      }
-      count += 1;
-      match &dat {
-        BotMode::Time(_,_) => {
-          if st.elapsed() >= maxtime.unwrap() {
-            break;
-          }
-        },
-        BotMode::Count(_,_) => {
-          if count >= maxcount.unwrap() {
-            break;
-          }
-        }
+fn describe_event(event: &GameEvent<RosterPlayer>) -> String {
+  match event {
+    GameEvent::SubboardWon(path,player) => format!("{} won {}",player.team_name(),describe_path(path)),
+    GameEvent::SubboardDrawn(path) => format!("{} is a draw",describe_path(path)),
+    GameEvent::JumpChanged(path,jump) => {
+      let cids: Vec<String> = jump.iter().map(|pos| pos.get_cid().to_string()).collect();
+      if !path.is_empty() {
+        format!("next move in {}: {}",describe_path(path),cids.join(","))
+      } else {
+        format!("next move in: {}",cids.join(","))
       }
-    }
-    move_score.push(MoveScore(score,Rc::clone(m)))
+    },
+    GameEvent::QuantumCollapse(path,cells) => format!("{} collapsed {} cells",describe_path(path),cells.len()),
+    GameEvent::GameOver(GameResult::Won(player)) => format!("{} wins the game!",player.team_name()),
+    GameEvent::GameOver(_) => String::from("The game is a draw"),
   }
-  let mut biggest = std::f64::MIN;
-  let mut bm: Rc<dyn Any> = Rc::new(true);
-  for m in move_score.iter() {
-    if m.0 > biggest {
-      bm = Rc::clone(&m.1);
-      biggest = m.0;
+}
+
+type Game = SuperDummyGame<Box<dyn DynGame<RosterPlayer>>,RosterPlayer>;
+
+/// Ends the game if someone's flag has fallen. Returns whether it did.
+fn check_flag(game: &mut Game, clock: &Option<GameClock<RosterPlayer>>, events: &Mutex<GameEvents<RosterPlayer>>) -> bool {
+  if let (SubgameState::Playing(_), Some(clock)) = (game.get_game_ref(), clock) {
+    let result = clock.result();
+    if let GameResult::Incomplete = result {
+      false
+    } else {
+      game.end(result);
+      events.lock().unwrap().emit_event(&GameEvent::GameOver(result));
+      true
     }
+  } else {
+    false
   }
-  Some(bm)
 }
 
-fn good_size(mw: usize,mh: usize) -> (usize,usize) {
-  loop {
-    let (w,h) = termion::terminal_size().expect("error!");
-    if ((w as usize) >= mw) && ((h as usize) >= mh) {
-      return (w as usize,h as usize);
-    }
+/// How long a bot may think with `remaining` on its clock.
+fn time_budget(remaining: Duration, control: TimeControl) -> Duration {
+  let budget = min(remaining/2, remaining/30 + control.increment*3/4);
+  max(Duration::from_millis(1),budget)
+}
+
+fn format_clocks(clock: &GameClock<RosterPlayer>, roster: &'static Roster) -> String {
+  let running = clock.get_running().map(|p| p.get_id());
+  let clocks: Vec<String> = roster.players().iter().map(|p| {
+    let t = clock.remaining(*p);
+    format!("{}{} {}:{:02}.{}",
+      if running == Some(p.get_id()) {"*"} else {""},
+      p.name(), t.as_secs()/60, t.as_secs()%60, t.subsec_millis()/100)
+  }).collect();
+  clocks.join("  ")
+}
+
+/// A fresh game of `spec`, started from `setup` if there is one.
+fn new_game(spec: &GameSpec, roster: &'static Roster, setup: &Option<Setup>) -> Result<Game,String> {
+  let mut game = spec.build(roster.first());
+  let mut result = GameResult::Incomplete;
+  if let Some(setup) = setup {
+    result = setup.apply(&mut game, |name| roster.find(name))?;
   }
+  let mut game = Game::new(game);
+  game.end(result);
+  Ok(game)
+}
+
+/// The game's setup with `moves` played after it, to save the game or to
+/// go back to an earlier move of it.
+fn replay_setup(spec: &GameSpec, setup: &Option<Setup>, moves: &[String]) -> Setup {
+  let mut setup = setup.clone().unwrap_or_default();
+  setup.game = Some(spec.clone());
+  for m in moves.iter() {
+    setup.push_move(m.clone());
+  }
+  setup
+}
+
+/// Seeds for the bots of `n` seats in the game played from `seed`.
+fn seat_seeds(seed: u64, n: usize) -> Vec<u64> {
+  let mut rng = StdRng::seed_from_u64(seed);
+  (0..n).map(|_| rng.gen()).collect()
+}
+
+/// A bot for every player in the roster, all sharing `book`.
+fn new_bots(configs: &[BotConfig], threads: usize, book: &Option<Arc<OpeningBook>>) -> Vec<Bot<Game,RosterPlayer>> {
+  configs.iter().map(|config| {
+    let mut bot = Bot::new(config.clone(),threads);
+    bot.book = book.clone();
+    bot
+  }).collect()
 }
 
 const MAX_DT_IDLE: u64 = 1000/10;
 const MAX_DT_SCROLL: u64 = 1000/1000;
+/// How long a cell that couldn't be played stays red after a click.
+const REFUSED_FLASH: Duration = Duration::from_millis(600);
+/// How long the bot thinks about a hint.
+const HINT_TIME: Duration = Duration::from_millis(1000);
+/// The hinted move's cells and the boards they're in.
+const HINT_COLOR: ColorEnum = ColorEnum::LightYellow;
+
+/// Where the player after `m` will have to move, for a hint to say.
+fn describe_jump(game: &Game, m: &Rc<dyn Any>) -> String {
+  let mut next = game.clone();
+  if let GameResult::Incomplete = next.mov(Rc::clone(m)).result {} else {
+    return String::from("ends the game");
+  }
+  let mut boards: Vec<u8> = next.get_valid_moves().iter()
+    .filter_map(|m| m.downcast_ref::<SuperTicTacToeMove>().map(|m| m.pos.get_cid())).collect();
+  boards.sort();
+  boards.dedup();
+  // boards still being played, to tell a free choice from a jump
+  let open = match next.get_game_ref().get_game_ref().and_then(|g| (**g).as_any().downcast_ref::<MixedSuperTicTacToe<RosterPlayer>>()) {
+    Some(board) => (0..9).filter(|&cid| board.get_cell_ref(Position::from_cid(cid)).get_game_ref().is_some()).count(),
+    None => 0,
+  };
+  let who = next.get_player().name();
+  match boards.len() {
+    0 => format!("{} moves next",who),
+    n if n == open && n > 1 => format!("{} can go anywhere",who),
+    1 => format!("sends {} to board {}",who,boards[0]),
+    _ => format!("sends {} to boards {}",who,boards.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(", ")),
+  }
+}
 
 fn main() {
+  let opts = match Options::from_args(std::env::args().skip(1)) {
+    Ok(Some(opts)) => opts,
+    Ok(None) => {
+      println!("{}",USAGE);
+      return;
+    },
+    Err(e) => {
+      eprintln!("guttt: {}\n{}",e,USAGE);
+      std::process::exit(1);
+    }
+  };
+  if let Some(path) = &opts.make_book {
+    if let Err(e) = book::make(&opts,path) {
+      eprintln!("guttt: {}",e);
+      std::process::exit(1);
+    }
+    return;
+  }
+  if opts.tournament {
+    if let Err(e) = tournament::run(&opts) {
+      eprintln!("guttt: {}",e);
+      std::process::exit(1);
+    }
+    return;
+  }
   {
     type PanicFunc = Box<dyn Fn(&std::panic::PanicInfo) + Sync + Send + 'static>;
     static mut oldhook: MaybeUninit<PanicFunc> = MaybeUninit::uninit();
@@ -155,80 +220,183 @@ fn main() {
       std::process::abort();
     }));
   }
-  let gamegen = || {
-    SuperDummyGame::<
-      SuperTicTacToe<
-        //SuperTicTacToe<
-          //SuperTicTacToe<DummyGame<pl_XO>,pl_XO>,
-          QuantumTicTacToe<pl_XO>,
-        //pl_XO>,
-      pl_XO>,
-    pl_XO>::create(X,Default::default())
+  let spec = opts.game;
+  let roster = opts.roster;
+  let setup = opts.setup;
+  let gamegen = {
+    let (spec,setup) = (spec.clone(),setup.clone());
+    move || new_game(&spec,roster,&setup)
+  };
+  let first_game = match gamegen() {
+    Ok(game) => game,
+    Err(e) => {
+      eprintln!("guttt: bad setup: {}",e);
+      std::process::exit(1);
+    }
   };
-  let game_mutex: Arc<Mutex<_>> = Arc::new(Mutex::new(gamegen()));
+  let game_mutex: Arc<Mutex<_>> = Arc::new(Mutex::new(first_game));
   let gmut = Arc::clone(&game_mutex);
+  let time = opts.time;
+  let threads = opts.threads;
+  let bot_configs = opts.bots.clone();
+  let book = opts.book.clone();
+  let ponder = opts.ponder;
+  let humans = opts.humans.clone();
+  let hmut = humans.clone();
+  // moves typed in, with the turn they were meant for
+  let (human_tx,human_rx) = channel::<(usize,usize)>();
+  let turn = Arc::new(AtomicUsize::new(0));
+  let tmut = Arc::clone(&turn);
+  // moves played since the setup, in notation
+  let history = Arc::new(Mutex::new(Vec::<String>::new()));
+  let hsmut = Arc::clone(&history);
+  let seed = Arc::new(Mutex::new(opts.seed.unwrap_or_else(rand::random)));
+  let smut = Arc::clone(&seed);
+  let clockgen = move || {
+    time.map(|control| GameClock::new(roster.players(),control))
+  };
+  let clock_mutex = Arc::new(Mutex::new(clockgen()));
+  let cmut = Arc::clone(&clock_mutex);
   let (tx,rx) = channel::<bool>();
+  let status = Arc::new(Mutex::new(String::new()));
+  let events = Arc::new(Mutex::new(GameEvents::<RosterPlayer>::new()));
+  let emut = Arc::clone(&events);
+  let analysis = Arc::new(Mutex::new(None::<analysis::Analysis>));
+  let amut = Arc::clone(&analysis);
+  {
+    let status = Arc::clone(&status);
+    events.lock().unwrap().listen(move |event| {
+      if let GameEvent::JumpChanged(_,_) = event {
+        return;
+      }
+      *status.lock().unwrap() = describe_event(event);
+    });
+  }
   let gamer_thread = thread::spawn(move || {
-    let mut rng = rand::thread_rng();
+    // every game after the first gets its own seed, so each can be replayed
+    let mut game_seeds = StdRng::seed_from_u64(*smut.lock().unwrap());
+    let seeds = seat_seeds(*smut.lock().unwrap(),bot_configs.len());
+    let mut players = roster.players();
+    players.sort_by_key(|p| p.get_id());
+    let seats: Vec<Option<seat::BotSeat>> = players.iter().map(|player| {
+      let id = player.get_id();
+      if hmut[id] {
+        None
+      } else {
+        Some(seat::BotSeat::spawn(*player,bot_configs[id].clone(),threads,book.clone(),ponder,seeds[id]))
+      }
+    }).collect();
+    // the turn the seats last heard about, and a move typed for a turn
+    // the loop below hasn't got to yet
+    let mut known = tmut.load(Ordering::SeqCst);
+    let mut held: Option<(usize,usize)> = None;
     loop {
       if let Some(msg) = rx.try_recv().ok() {
         break;
       }
-      //println!("> lg");
       let mut game = gmut.lock().unwrap();
-      //println!("> slg");
       if let SubgameState::Playing(_) = game.get_game_ref() {
-        let mut gameclone = (&*game).clone();
+        let gameclone = (*game).clone();
         let player = gameclone.get_player();
-        //println!("> lgd");
+        let started = tmut.load(Ordering::SeqCst);
+        if started != known {
+          // someone took moves back
+          for seat in seats.iter().flatten() {
+            seat.forget();
+          }
+          known = started;
+        }
         drop(game);
 
-         /*
-        game = gmut.lock().unwrap();
-        let moves = game.get_valid_moves();
-        let rmove = moves.iter().choose(&mut rng);
-        if let Some(rm) = rmove {
-          game.mov(Rc::clone(rm));
-        } else {
-          break;
+        let mut limit = None;
+        if let Some(clock) = &mut *cmut.lock().unwrap() {
+          clock.start(player);
+          limit = Some(time_budget(clock.remaining(player),clock.get_control()));
         }
-        drop(game);
-        thread::sleep(Duration::from_millis(100));
-         */
 
-        // /*
-        if let Some(m) = best_move(gameclone,player,&mut rng,
-          BotMode::Time(300,None)
-        ) {
-          //println!("> lg2");
+        let index = if hmut[player.get_id()] {
+          // the render loop sends what the player picks
+          loop {
+            if let Some((t,index)) = held.take() {
+              if t == started {
+                break Some(index);
+              }
+            }
+            match human_rx.recv_timeout(Duration::from_millis(50)) {
+              Ok((t,index)) if t == started => break Some(index),
+              Ok((t,index)) if t == tmut.load(Ordering::SeqCst) => {
+                held = Some((t,index));
+                break None;
+              },
+              Ok(_) => {},
+              Err(RecvTimeoutError::Timeout) => {
+                if tmut.load(Ordering::SeqCst) != started {
+                  break None;
+                }
+                if let SubgameState::Playing(_) = gmut.lock().unwrap().get_game_ref() {} else {
+                  break None;
+                }
+              },
+              Err(RecvTimeoutError::Disconnected) => break None,
+            }
+          }
+        } else {
+          let (index,analysis) = seats[player.get_id()].as_ref().expect("wtf?").choose(&gameclone,limit);
+          if let Some(analysis) = analysis {
+            *amut.lock().unwrap() = Some(analysis);
+          }
+          index
+        };
+        if let Some(m) = index.and_then(|i| gameclone.get_valid_moves().get(i).map(Rc::clone)) {
+          let index = index.unwrap();
           game = gmut.lock().unwrap();
-          //println!("> slg2");
-          if (game.is_valid_move(Rc::clone(&m))) {
-            game.mov(m);
+          let mut clock = cmut.lock().unwrap();
+          if !check_flag(&mut game,&clock,&emut) {
+            let current = tmut.load(Ordering::SeqCst) == started;
+            if let SubgameState::Playing(_) = game.get_game_ref() {
+              if current && game.is_valid_move(Rc::clone(&m)) {
+                for seat in seats.iter().flatten() {
+                  seat.advance(&game,index);
+                }
+                hsmut.lock().unwrap().push(move_notation(&m));
+                let mdata = game.mov(Rc::clone(&m));
+                known = tmut.fetch_add(1,Ordering::SeqCst)+1;
+                emut.lock().unwrap().emit(&mdata);
+              }
+            }
+            if let Some(clock) = &mut *clock {
+              if current {
+                clock.stop();
+              }
+            }
           }
-          //println!("> lgd2");
           drop(game);
         }
-        // */
 
       } else {
-        //println!("> lgd");
         drop(game);
         thread::sleep(Duration::from_millis(10000));
-        //println!("> lg3");
         game = gmut.lock().unwrap();
-        //println!("> slg3");
-        *game = gamegen();
+        *game = gamegen().unwrap();
+        hsmut.lock().unwrap().clear();
+        known = tmut.fetch_add(1,Ordering::SeqCst)+1;
+        let seed = game_seeds.gen();
+        *smut.lock().unwrap() = seed;
+        let seeds = seat_seeds(seed,bot_configs.len());
+        for (seat,seed) in seats.iter().zip(seeds) {
+          if let Some(seat) = seat {
+            seat.reset(seed);
+          }
+        }
+        *amut.lock().unwrap() = None;
+        *cmut.lock().unwrap() = clockgen();
       }
     }
   });
   let (mut w,mut h) = good_size(5,6);
   let (mut vw,mut vh) = (w-4,h-5);
-  //println!("lg");
   let game = game_mutex.lock().unwrap();
-  //println!("slg");
   let (mut rw,mut rh) = game.get_size();
-  //println!("lgd");
   drop(game);
   rw = max(min(vw,vh*2),rw);
   rh = max(min(vw/2,vh),rh);
@@ -242,20 +410,29 @@ fn main() {
   let mut off: (isize,isize) = (0,0);
   let mut running = true;
   
-  let mut screen = AlternateScreen::from(stdout()).into_raw_mode().unwrap();
+  let mut screen = MouseTerminal::from(AlternateScreen::from(stdout()).into_raw_mode().unwrap());
   write!(screen,"{}",cursor::Hide);
-  let mut input = termion::async_stdin().keys();
+  let mut input = termion::async_stdin().events();
   let mut fps = 0;
   let mut toff: (isize,isize) = (0,0);
+  let mut show_analysis = false;
+  let mut hand = InputHandle::default();
+  let mut click: Option<rendering::Locate> = None;
+  // what's typed after ':', and the turn a hint was asked for
+  let mut command: Option<String> = None;
+  let mut hint_turn: Option<usize> = None;
+  let mut hint: Option<(usize,Vec<Vec<Position>>)> = None;
+  // hears of no moves, so it forgets the last position before each hint
+  let hinter = seat::BotSeat::spawn(roster.first(),BotConfig{time: HINT_TIME, ..BotConfig::default()},threads,None,false,*seed.lock().unwrap());
+  let mut refused: Option<(Vec<Position>,Instant)> = None;
   let id = ["-","\\","|","/"];
   while running {
     let fstart = Instant::now();
-    //println!("lg2");
-    let game = game_mutex.lock().unwrap();
+    let mut game = game_mutex.lock().unwrap();
+    check_flag(&mut game,&clock_mutex.lock().unwrap(),&events);
     if (fstart.elapsed().as_millis() > 500) {
       panic!("Waiting too much");
     }
-    //println!("slg2");
     let (w2,h2) = good_size(5,6);
     if ((w != w2) || (h != h2)) {
       term = TerminalCanvas::new(w2,h2,ColorEnum::Green,ColorEnum::Reset);
@@ -275,8 +452,70 @@ fn main() {
       rh = rs/2;
       canv = TerminalCanvas::new(rw,rh,ColorEnum::Reset,ColorEnum::Reset);
     }
-    game.render(&mut canv,vec!());
-    //println!("lgd2");
+    let human_turn = match game.get_game_ref() {
+      SubgameState::Playing(sgame) => humans[sgame.get_player().get_id()],
+      _ => false,
+    };
+    if let Some(t) = hint_turn {
+      if let Some((index,_)) = hinter.answer() {
+        hint_turn = None;
+        if let (Some(m),true) = (index.and_then(|i| game.get_valid_moves().get(i).map(Rc::clone)),t == turn.load(Ordering::SeqCst)) {
+          *status.lock().unwrap() = format!("Hint for {}: {}, which {}",game.get_player().name(),move_notation(&m),describe_jump(&game,&m));
+          hint = Some((t,move_cells(&m)));
+        }
+      }
+    }
+    let prompt = match game.get_game_ref() {
+      SubgameState::Playing(sgame) if human_turn => format!("{} to move   ",sgame.get_player().name()),
+      _ => String::new(),
+    };
+    let mut highlight = rendering::Highlight(vec!());
+    if human_turn {
+      highlight.0.extend(hand.highlight(&*game).0);
+    } else {
+      hand.reset();
+    }
+    if let Some((t,cells)) = &hint {
+      if *t == turn.load(Ordering::SeqCst) {
+        // the cells and every board on the way to them
+        for path in cells.iter() {
+          for depth in 1..=path.len() {
+            highlight.0.push((path[..depth].to_vec(),HINT_COLOR));
+          }
+        }
+      }
+    }
+    if show_analysis {
+      if let Some(analysis) = &*analysis.lock().unwrap() {
+        highlight.0.extend(analysis.highlight().0);
+      }
+    }
+    if let Some((path,when)) = &refused {
+      if when.elapsed() < REFUSED_FLASH {
+        highlight.0.push((path.clone(),ColorEnum::Red));
+      }
+    }
+    let mut args: Vec<Rc<dyn Any>> = vec!(Rc::new(highlight));
+    if let Some(at) = click.take() {
+      if human_turn {
+        args.push(Rc::new(at));
+      }
+    }
+    let rvs = game.render(&mut canv,args);
+    if let Some(rendering::Located(path)) = rvs.iter().filter_map(|rv| rv.downcast_ref::<rendering::Located>()).next() {
+      match hand.click(&*game,path) {
+        Ok(Some(m)) => {
+          if let Some(index) = game.get_valid_moves().iter().position(|o| game.is_same_move(o,&m)) {
+            human_tx.send((turn.load(Ordering::SeqCst),index)).ok();
+          }
+        },
+        Ok(None) => (),
+        Err(reason) => {
+          *status.lock().unwrap() = reason;
+          refused = Some((path.clone(),Instant::now()));
+        },
+      }
+    }
     if let SubgameState::Playing(sgame) = game.get_game_ref() {
       let player = sgame.get_player();
       term.clip(1,h-1,1,1);
@@ -295,10 +534,23 @@ fn main() {
     ).as_str());
     term.clip(1,h-1,1,1);
     term.unclip();
-    term.set(3,(h as isize)-1,format!("FPS: {} {}   ",fps,id[n]).as_str());
+    let message = match &command {
+      Some(text) => format!(":{}_",text),
+      None => format!("{}{}",prompt,status.lock().unwrap()),
+    };
+    let clocks = match &*clock_mutex.lock().unwrap() {
+      Some(clock) => format!("{}   ",format_clocks(clock,roster)),
+      None => String::new(),
+    };
+    term.set(3,(h as isize)-1,format!("{:1$}",format!("FPS: {} {}   seed {}   {}{}",fps,id[n],seed.lock().unwrap(),clocks,message),w-3).as_str());
     term.clip(2,2,vw,vh);
     term.set_canv(-ox,-oy,&canv);
     term.unclip();
+    if show_analysis {
+      if let Some(analysis) = &*analysis.lock().unwrap() {
+        analysis.render(&mut term,8);
+      }
+    }
     {
       write!(screen,"{}{}", cursor::Goto(1,1),term);
       screen.flush().unwrap();
@@ -306,17 +558,144 @@ fn main() {
     n=(n+1)%4;
     let (mut tox, mut toy) = toff;
     let ms = min(vw,vh) as isize;
+    let mut entered: Option<String> = None;
     loop {
-      match input.next() {
-        Some(key) => match key.unwrap() {
-          Key::Up => {toy = toy-(vh as isize)/2},
-          Key::Down => {toy = toy+(vh as isize)/2},
-          Key::Left => {tox = tox-(vw as isize)/2},
-          Key::Right => {tox = tox+(vw as isize)/2},
-          Key::Esc => {running = false;},
-          _ => (),
+      let control = match input.next() {
+        Some(Ok(Event::Key(key))) if command.is_some() => {
+          let text = command.as_mut().unwrap();
+          match key {
+            Key::Char('\n') => {entered = command.take();},
+            Key::Char(c) => text.push(c),
+            Key::Backspace => {text.pop();},
+            Key::Esc => {command = None;},
+            _ => (),
+          }
+          None
+        },
+        Some(Ok(Event::Mouse(MouseEvent::Press(button,x,y)))) => {
+          let (tx,ty) = ((x as isize)-1,(y as isize)-1);
+          let inside = tx >= 2 && ty >= 2 && tx < 2+(vw as isize) && ty < 2+(vh as isize);
+          match button {
+            MouseButton::Left if inside && human_turn => {
+              click = Some(rendering::Locate(tx-2+ox,ty-2+oy));
+            },
+            MouseButton::WheelUp => {toy = toy-(vh as isize)/4},
+            MouseButton::WheelDown => {toy = toy+(vh as isize)/4},
+            _ => (),
+          }
+          None
+        },
+        Some(Ok(Event::Key(key))) => match key {
+          Key::Up if human_turn => Some(Control::Up),
+          Key::Down if human_turn => Some(Control::Down),
+          Key::Left if human_turn => Some(Control::Left),
+          Key::Right if human_turn => Some(Control::Right),
+          Key::Char('\n') if human_turn => Some(Control::Push),
+          Key::Backspace if human_turn => Some(Control::Pop),
+          key => {
+            match key {
+              Key::Up | Key::PageUp => {toy = toy-(vh as isize)/2},
+              Key::Down | Key::PageDown => {toy = toy+(vh as isize)/2},
+              Key::Left | Key::Home => {tox = tox-(vw as isize)/2},
+              Key::Right | Key::End => {tox = tox+(vw as isize)/2},
+              Key::Esc => {running = false;},
+              Key::Char('a') => {show_analysis = !show_analysis;},
+              Key::Char(':') => {command = Some(String::new());},
+              Key::Char('h') => {entered = Some(String::from("hint"));},
+              _ => (),
+            }
+            None
+          },
         },
+        Some(_) => None,
         None => {break;}
+      };
+      if let Some(control) = control {
+        let game = game_mutex.lock().unwrap();
+        if let Some(m) = hand.input(&*game,control) {
+          if let Some(index) = game.get_valid_moves().iter().position(|o| game.is_same_move(o,&m)) {
+            human_tx.send((turn.load(Ordering::SeqCst),index)).ok();
+          }
+        }
+      }
+    }
+    if let Some(text) = entered {
+      let words: Vec<&str> = text.split_whitespace().collect();
+      let reply = match words.as_slice() {
+        [] => None,
+        ["undo"] => {
+          let mut game = game_mutex.lock().unwrap();
+          let mut moves = history.lock().unwrap();
+          if let SubgameState::Playing(_) = game.get_game_ref() {
+            // back to the last time someone at the keyboard was to move
+            let mut n = moves.len();
+            let mut earlier = Ok(None);
+            while n > 0 {
+              n -= 1;
+              earlier = new_game(&spec,roster,&Some(replay_setup(&spec,&setup,&moves[..n]))).map(|g| {
+                if humans[g.get_player().get_id()] {Some(g)} else {None}
+              });
+              match earlier {
+                Ok(None) => {},
+                _ => break,
+              }
+            }
+            match earlier {
+              Err(e) => Some(format!("Can't go back: {}",e)),
+              Ok(Some(g)) => {
+                let taken = moves.len()-n;
+                moves.truncate(n);
+                *game = g;
+                turn.fetch_add(1,Ordering::SeqCst);
+                hand.reset();
+                *analysis.lock().unwrap() = None;
+                Some(format!("Took back {} move{}",taken,if taken == 1 {""} else {"s"}))
+              },
+              Ok(None) => Some(String::from("There's no move to take back")),
+            }
+          } else {
+            Some(String::from("The game is over"))
+          }
+        },
+        ["save",path] => {
+          let mut saved = replay_setup(&spec,&setup,&history.lock().unwrap());
+          saved.seed = Some(*seed.lock().unwrap());
+          Some(match std::fs::write(path,saved.to_string()) {
+            Ok(_) => format!("Saved to {}, play on with --setup {}",path,path),
+            Err(e) => format!("Can't write {}: {}",path,e),
+          })
+        },
+        ["save"] => Some(String::from("save needs a file to write")),
+        ["hint"] => {
+          let game = game_mutex.lock().unwrap();
+          if hint_turn.is_some() {
+            Some(String::from("Still thinking about the last hint"))
+          } else if let SubgameState::Playing(_) = game.get_game_ref() {
+            hinter.forget();
+            hinter.ask(&game,Some(HINT_TIME));
+            hint_turn = Some(turn.load(Ordering::SeqCst));
+            Some(String::from("Thinking..."))
+          } else {
+            Some(String::from("The game is over"))
+          }
+        },
+        _ if !human_turn => Some(String::from("It's not your move")),
+        _ => {
+          let game = game_mutex.lock().unwrap();
+          let moves = game.get_valid_moves();
+          match find_move(&moves,&text) {
+            Ok(m) => {
+              let index = moves.iter().position(|o| game.is_same_move(o,&m)).expect("wtf?");
+              human_tx.send((turn.load(Ordering::SeqCst),index)).ok();
+              hand.reset();
+              None
+            },
+            Err(e) => Some(e),
+          }
+        },
+      };
+      if let Some(reply) = reply {
+        *status.lock().unwrap() = reply;
       }
     }
     tox = min((rw as isize)-(vw as isize),max(0,tox));
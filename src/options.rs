@@ -0,0 +1,357 @@
+use crate::tic_tac_toe::*;
+use crate::roster::*;
+use crate::tournament::Entrant;
+use crate::terminal_render::ColorEnum;
+use std::fs;
+use std::sync::Arc;
+
+pub const USAGE: &str = "\
+usage: guttt [options]
+  -g, --game SPEC       game to play, e.g. S(Q) or S(S(T)) (default S(Q))
+      --game-file PATH  read the game spec from a file
+  -p, --player PLAYER   add a player as NAME[:GLYPH[:COLOUR]], in turn order;
+                        GLYPH is cross, ring, crescent or any character
+                        (default X:cross:lightred O:ring:lightblue
+                        C:crescent:lightgreen)
+      --order NAMES     comma separated turn order, e.g. O,X,C
+      --team NAMES      comma separated players whose marks make lines
+                        together, e.g. X,C; may be given more than once
+      --setup PATH      start from the position described in a file
+      --setup-str TEXT  the same, with the setup given inline
+  -t, --time BASE[+INC] give every player BASE seconds on their clock
+                        and INC more after each of their moves
+  -b, --bot [NAME=]BOT  who plays NAME's seat, or every seat without NAME;
+                        BOT is human to play it at the keyboard, so
+                        -b human is everyone taking turns at one, or
+                        random, easy, medium, hard or max, or
+                        random, flat, mcts or alphabeta, then any of
+                        ,time=MS ,playouts=N ,c=EXPLORATION ,depth=N
+                        ,solve=true|false
+                        and for what it plays for ,win=V ,draw=V ,loss=V
+                        ,loss.NAME=V ,objective=score|winrate|paranoid
+                        (default medium)
+      --human NAME      the same as -b NAME=human; arrows move the cursor,
+                        Enter goes into a board or marks a cell and
+                        Backspace goes back out; or click a cell
+  -j, --threads N       threads the bots search with (default: one per core)
+      --no-ponder       don't let bots think on the other players' turns
+      --seed N          where the bots' luck comes from (default: random,
+                        shown during the game); the same seed plays the
+                        same game again only with --no-ponder and bots
+                        given playouts= instead of a time
+      --tournament      play bots against each other without the board
+                        and print the results, with two players unless
+                        -p says otherwise
+  -e, --entrant BOT     add a bot to the tournament, written as for --bot
+      --gauntlet        the first entrant plays each of the others,
+                        instead of everyone playing everyone
+      --games N         games per pairing, half started by each side
+                        (default 20)
+      --book PATH       bots play from this opening book while they can
+      --make-book PATH  search the first moves of the game with the bots
+                        given by -b and write what they found to PATH
+      --book-depth N    how many moves deep --make-book goes (default 3)
+  -h, --help            show this message";
+
+pub struct Options {
+  pub game: GameSpec,
+  pub roster: &'static Roster,
+  pub time: Option<TimeControl>,
+  pub setup: Option<Setup>,
+  pub threads: usize,
+  /// Bots keep thinking while someone else is to move.
+  pub ponder: bool,
+  /// Seed of the first game, or of the tournament or book.
+  pub seed: Option<u64>,
+  /// Bot for every player, by roster position.
+  pub bots: Vec<BotConfig>,
+  /// Whether each player is played from the keyboard, by roster position.
+  pub humans: Vec<bool>,
+  pub tournament: bool,
+  pub entrants: Vec<Entrant>,
+  pub gauntlet: bool,
+  pub games: u32,
+  pub book: Option<Arc<OpeningBook>>,
+  pub make_book: Option<String>,
+  pub book_depth: usize,
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Options {
+      game: GameSpec::Super(Box::new(GameSpec::Quantum)),
+      roster: Roster::new(Roster::default_entries(),vec!(),vec!()).unwrap().leak(),
+      time: None,
+      setup: None,
+      threads: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+      ponder: true,
+      seed: None,
+      bots: vec!(),
+      humans: vec!(),
+      tournament: false,
+      entrants: vec!(),
+      gauntlet: false,
+      games: 20,
+      book: None,
+      make_book: None,
+      book_depth: 3,
+    }
+  }
+}
+
+const PALETTE: [ColorEnum; 6] = [
+  ColorEnum::LightRed,
+  ColorEnum::LightBlue,
+  ColorEnum::LightGreen,
+  ColorEnum::LightYellow,
+  ColorEnum::LightMagenta,
+  ColorEnum::LightCyan,
+];
+
+/// Seat spec for a player at the keyboard instead of a bot.
+const HUMAN: &str = "human";
+
+fn value<I: Iterator<Item = String>>(args: &mut I, name: &str) -> Result<String,String> {
+  args.next().ok_or_else(|| format!("{} needs a value", name))
+}
+
+impl Options {
+  /// Parses command line arguments, not including the program name.
+  /// `Ok(None)` means help was asked for.
+  pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>,String> {
+    let mut opts = Options::default();
+    let mut players: Vec<RosterEntry> = vec!();
+    let mut order: Vec<String> = vec!();
+    let mut teams: Vec<Vec<String>> = vec!();
+    // bots are parsed once the players are known, for settings that name them
+    let mut bot: Option<String> = None;
+    let mut seat_bots: Vec<(String,String)> = vec!();
+    let mut entrants: Vec<String> = vec!();
+    let mut humans: Vec<String> = vec!();
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "-g" | "--game" => {
+          let spec = value(&mut args, &arg)?;
+          opts.game = spec.parse().map_err(|e| format!("bad game spec '{}': {}", spec, e))?;
+        },
+        "--game-file" => {
+          let path = value(&mut args, &arg)?;
+          let spec = fs::read_to_string(&path).map_err(|e| format!("can't read {}: {}", path, e))?;
+          opts.game = spec.parse().map_err(|e| format!("bad game spec in {}: {}", path, e))?;
+        },
+        "-p" | "--player" => {
+          let color = PALETTE[players.len()%PALETTE.len()];
+          players.push(RosterEntry::parse(&value(&mut args, &arg)?, color)?);
+        },
+        "--order" => {
+          order = value(&mut args, &arg)?.split(',').map(String::from).collect();
+        },
+        "--team" => {
+          teams.push(value(&mut args, &arg)?.split(',').map(String::from).collect());
+        },
+        "--setup" => {
+          let path = value(&mut args, &arg)?;
+          let text = fs::read_to_string(&path).map_err(|e| format!("can't read {}: {}", path, e))?;
+          opts.setup = Some(Setup::parse(&text).map_err(|e| format!("{}: {}", path, e))?);
+        },
+        "--setup-str" => {
+          opts.setup = Some(Setup::parse(&value(&mut args, &arg)?)?);
+        },
+        "-t" | "--time" => {
+          opts.time = Some(TimeControl::parse(&value(&mut args, &arg)?)?);
+        },
+        "-b" | "--bot" => {
+          let spec = value(&mut args, &arg)?;
+          match spec.find('=') {
+            Some(i) if !spec[..i].contains(',') => {
+              seat_bots.push((String::from(&spec[..i]), String::from(&spec[i+1..])));
+            },
+            _ => bot = Some(spec),
+          }
+        },
+        "--human" => humans.push(value(&mut args, &arg)?),
+        "-j" | "--threads" => {
+          let n = value(&mut args, &arg)?;
+          opts.threads = match n.parse() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("'{}' is not a thread count", n)),
+          };
+        },
+        "--no-ponder" => opts.ponder = false,
+        "--seed" => {
+          let n = value(&mut args, &arg)?;
+          opts.seed = Some(n.parse().map_err(|_| format!("'{}' is not a seed", n))?);
+        },
+        "--tournament" => opts.tournament = true,
+        "-e" | "--entrant" => entrants.push(value(&mut args, &arg)?),
+        "--gauntlet" => opts.gauntlet = true,
+        "--games" => {
+          let n = value(&mut args, &arg)?;
+          opts.games = match n.parse() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("'{}' is not a number of games", n)),
+          };
+        },
+        "--book" => {
+          let path = value(&mut args, &arg)?;
+          let text = fs::read_to_string(&path).map_err(|e| format!("can't read {}: {}", path, e))?;
+          opts.book = Some(Arc::new(OpeningBook::parse(&text).map_err(|e| format!("{}: {}", path, e))?));
+        },
+        "--make-book" => opts.make_book = Some(value(&mut args, &arg)?),
+        "--book-depth" => {
+          let n = value(&mut args, &arg)?;
+          opts.book_depth = n.parse().map_err(|_| format!("'{}' is not a number of moves", n))?;
+        },
+        "-h" | "--help" => return Ok(None),
+        _ => return Err(format!("unknown option '{}'", arg)),
+      }
+    }
+    if let Some(Setup{game: Some(game), ..}) = &opts.setup {
+      opts.game = game.clone();
+    }
+    if let Some(Setup{seed: Some(seed), ..}) = &opts.setup {
+      opts.seed = opts.seed.or(Some(*seed));
+    }
+    if let Some(book) = &opts.book {
+      if opts.setup.is_some() {
+        return Err(String::from("an opening book can't be used with a setup"));
+      }
+      let spec: GameSpec = book.game.parse().map_err(|e| format!("bad game spec in the book: {}", e))?;
+      if spec.to_string() != opts.game.to_string() {
+        return Err(format!("the book is for {}, not {}", spec, opts.game));
+      }
+    }
+    if players.is_empty() {
+      players = Roster::default_entries();
+      if opts.tournament {
+        players.truncate(2);
+      }
+    }
+    opts.roster = Roster::new(players, order, teams)?.leak();
+    let roster = opts.roster;
+    if let Some(book) = &opts.book {
+      // book lines go by who moves at each ply, so they only fit the same roster
+      if book.players != roster.names() {
+        return Err(format!("the book is for players {}, not {}", book.players.join(" "), roster.names().join(" ")));
+      }
+      if book.teams != roster.team_names() {
+        let show = |teams: Vec<String>| if teams.is_empty() {String::from("no teams")} else {format!("teams {}", teams.join(" "))};
+        return Err(format!("the book is for {}, not {}", show(book.teams.clone()), show(roster.team_names())));
+      }
+    }
+    let find_team = |name: &str| roster.find(name).map(|p| p.get_team());
+    // human seats keep a bot config, but it's never used
+    let hotseat = bot.as_ref().map(|spec| spec == HUMAN).unwrap_or(false);
+    let bot = match bot {
+      Some(spec) if !hotseat => BotConfig::parse(&spec, find_team)?,
+      _ => BotConfig::default(),
+    };
+    opts.bots = vec![bot; roster.len()];
+    opts.humans = vec![hotseat; roster.len()];
+    for (name,spec) in seat_bots.into_iter() {
+      let player = roster.find(&name).ok_or_else(|| format!("--bot: unknown player '{}'", name))?;
+      if spec == HUMAN {
+        opts.humans[player.get_id()] = true;
+      } else {
+        opts.bots[player.get_id()] = BotConfig::parse(&spec, find_team)?;
+        opts.humans[player.get_id()] = false;
+      }
+    }
+    for name in humans.iter() {
+      let player = roster.find(name).ok_or_else(|| format!("--human: unknown player '{}'", name))?;
+      opts.humans[player.get_id()] = true;
+    }
+    if (opts.tournament || opts.make_book.is_some()) && opts.humans.contains(&true) {
+      return Err(String::from("only bots can play in a tournament or make a book"));
+    }
+    for spec in entrants.iter() {
+      opts.entrants.push(Entrant::parse(spec, find_team)?);
+    }
+    Ok(Some(opts))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  fn parse(args: &[&str]) -> Result<Option<Options>,String> {
+    Options::from_args(args.iter().map(|a| String::from(*a)))
+  }
+
+  fn opts(args: &[&str]) -> Options {
+    parse(args).unwrap().expect("help instead of options")
+  }
+
+  fn error(args: &[&str]) -> String {
+    parse(args).err().expect("options instead of an error")
+  }
+
+  /// A book for S(T) between X and O, written to a file of its own.
+  fn book_file(name: &str) -> String {
+    let book = OpeningBook::new(String::from("S(T)"), vec![String::from("X"), String::from("O")], vec!());
+    let path = std::env::temp_dir().join(format!("guttt-{}-{}.book", name, std::process::id()));
+    fs::write(&path, book.to_string()).unwrap();
+    path.to_string_lossy().into_owned()
+  }
+
+  #[test]
+  fn defaults() {
+    let o = opts(&[]);
+    assert_eq!(o.game.to_string(), "S(Q)");
+    assert_eq!(o.roster.names(), vec!["X", "O", "C"]);
+    assert!(o.bots.iter().all(|b| b.algorithm == Algorithm::Mcts && b.time == Duration::from_millis(300)));
+    assert_eq!(o.humans, vec![false; 3]);
+    assert_eq!((o.seed, o.ponder), (None, true));
+    assert!(parse(&["-h"]).unwrap().is_none());
+  }
+
+  #[test]
+  fn bots_for_everyone_and_for_seats() {
+    let o = opts(&["-b", "hard", "-b", "O=random", "-b", "C=human", "--seed", "7", "--no-ponder", "-j", "2"]);
+    assert_eq!(o.bots[0].time, Duration::from_millis(1500));
+    assert_eq!(o.bots[1].algorithm, Algorithm::Random);
+    assert_eq!(o.humans, vec![false, false, true]);
+    assert_eq!((o.seed, o.ponder, o.threads), (Some(7), false, 2));
+    // settings naming players are read once the players are known
+    let o = opts(&["-b", "mcts,loss.O=-1.5", "-p", "X", "-p", "O"]);
+    assert_eq!(o.bots[0].scoring.losses, vec![(String::from("roster_1"), -1.5)]);
+    assert_eq!(opts(&["-b", "human"]).humans, vec![true; 3]);
+    assert_eq!(opts(&["--human", "O"]).humans, vec![false, true, false]);
+    assert_eq!(opts(&["-b", "human", "-b", "X=easy"]).humans, vec![false, true, true]);
+  }
+
+  #[test]
+  fn seeds_from_setups() {
+    assert_eq!(opts(&["--setup-str", "game S(T); seed 5"]).seed, Some(5));
+    assert_eq!(opts(&["--setup-str", "game S(T); seed 5", "--seed", "9"]).seed, Some(9));
+    assert_eq!(opts(&["--setup-str", "game S(T); seed 5"]).game.to_string(), "S(T)");
+  }
+
+  #[test]
+  fn books_fit_their_game_and_roster() {
+    let path = book_file("fit");
+    assert!(opts(&["-g", "S(T)", "-p", "X", "-p", "O", "--book", &path]).book.is_some());
+    assert_eq!(error(&["-g", "S(Q)", "-p", "X", "-p", "O", "--book", &path]), "the book is for S(T), not S(Q)");
+    assert_eq!(error(&["-g", "S(T)", "--book", &path]), "the book is for players X O, not X O C");
+    assert_eq!(error(&["-g", "S(T)", "-p", "X", "-p", "O", "--order", "O,X", "--book", &path]), "the book is for players X O, not O X");
+    assert_eq!(error(&["-g", "S(T)", "-p", "X", "-p", "O", "--book", &path, "--setup-str", "mark 4 4 X"]), "an opening book can't be used with a setup");
+    fs::remove_file(&path).ok();
+  }
+
+  #[test]
+  fn says_what_is_wrong() {
+    assert_eq!(error(&["--seed", "x"]), "'x' is not a seed");
+    assert_eq!(error(&["--seed"]), "--seed needs a value");
+    assert_eq!(error(&["-j", "0"]), "'0' is not a thread count");
+    assert_eq!(error(&["--games", "0"]), "'0' is not a number of games");
+    assert_eq!(error(&["--frobnicate"]), "unknown option '--frobnicate'");
+    assert_eq!(error(&["-b", "Z=easy"]), "--bot: unknown player 'Z'");
+    assert_eq!(error(&["--human", "Z"]), "--human: unknown player 'Z'");
+    assert_eq!(error(&["-b", "mcts,loss.Z=0"]), "bot setting loss.Z: unknown player 'Z'");
+    assert_eq!(error(&["--tournament", "-b", "X=human"]), "only bots can play in a tournament or make a book");
+    assert!(error(&["-g", "S(T"]).starts_with("bad game spec 'S(T': "));
+    assert!(error(&["--book", "/nonexistent/guttt.book"]).starts_with("can't read /nonexistent/guttt.book: "));
+  }
+}
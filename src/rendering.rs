@@ -1,11 +1,10 @@
 use termion::color;
 use termion::clear;
 use termion::cursor;
-use crate::pl_xo::pl_XO;
+use crate::roster::{RosterPlayer,Glyph};
 use crate::terminal_render::*;
 use crate::tic_tac_toe::*;
 use std::any::Any;
-use pl_XO::*;
 use std::cmp::{min,max};
 use std::ops::Range;
 use std::rc::Rc;
@@ -15,6 +14,76 @@ use std::rc::Rc;
 struct DontRenderSelection;
 struct ShadowedRender;
 
+/// A point on the canvas a board is rendered on, to find the cell under
+/// it, like where a mouse click went.
+#[derive(Copy, Clone, Debug)]
+pub struct Locate(pub isize,pub isize);
+
+/// Path to the cell under a `Locate` point, given back by render. Ends at a
+/// board when the point is on it but not in any of its cells.
+#[derive(Clone, Debug)]
+pub struct Located(pub Vec<Position>);
+
+impl Locate {
+  fn find(args: &Vec<Rc<dyn Any>>) -> Option<Locate> {
+    args.iter().filter_map(|rv| rv.downcast_ref::<Locate>()).next().cloned()
+  }
+
+  /// The point relative to the `w` by `h` rectangle at `(x,y)`, if it's in
+  /// there.
+  fn within(&self, x: usize, y: usize, w: usize, h: usize) -> Option<Locate> {
+    let (px,py) = (self.0-x as isize,self.1-y as isize);
+    if px >= 0 && py >= 0 && px < w as isize && py < h as isize {
+      Some(Locate(px,py))
+    } else {
+      None
+    }
+  }
+}
+
+impl Located {
+  fn find(rvs: &Vec<Rc<dyn Any>>) -> Option<&Located> {
+    rvs.iter().filter_map(|rv| rv.downcast_ref::<Located>()).next()
+  }
+
+  fn give(path: Option<Vec<Position>>) -> Vec<Rc<dyn Any>> {
+    match path {
+      Some(path) => vec!(Rc::new(Located(path))),
+      None => vec!(),
+    }
+  }
+}
+
+/// Cells to colour in, by their path from the board being rendered. A path
+/// that ends at a board colours that board's frame.
+#[derive(Clone)]
+pub struct Highlight(pub Vec<(Vec<Position>,ColorEnum)>);
+
+impl Highlight {
+  fn find(args: &Vec<Rc<dyn Any>>) -> Option<&Highlight> {
+    args.iter().filter_map(|rv| rv.downcast_ref::<Highlight>()).next()
+  }
+
+  /// The part of the highlight inside the board at `pos`.
+  fn inside(&self, pos: Position) -> Highlight {
+    Highlight(self.0.iter().filter(|(path,_)| path.len() > 1 && path[0] == pos)
+      .map(|(path,color)| (path[1..].to_vec(),*color)).collect())
+  }
+
+  /// The colour of the cell at `pos` itself, if it has one.
+  fn at(&self, pos: Position) -> Option<ColorEnum> {
+    self.0.iter().find(|(path,_)| path.len() == 1 && path[0] == pos).map(|(_,color)| *color)
+  }
+
+  /// Paints the `w` by `h` cell at `(x,y)` before its contents go on top.
+  fn fill(term: &mut TerminalCanvas, x: usize, y: usize, w: usize, h: usize, color: ColorEnum) {
+    let (fg,bg) = term.get_color();
+    term.set_bg(color);
+    term.set(x as isize,y as isize,format!("{}\r\n",(" ").repeat(w)).repeat(h).as_str());
+    term.set_color(fg,bg);
+  }
+}
+
 impl<T: GenericGame<P> + TerminalRender,P: Player + TerminalRender> TerminalRender for SuperTicTacToe<T,P> {
   fn get_size(&self) -> (usize,usize) {
     let (mut mw, mut mh) = (0,0);
@@ -36,10 +105,12 @@ impl<T: GenericGame<P> + TerminalRender,P: Player + TerminalRender> TerminalRend
     let (cw,ch) = (pcw+2,pch);
     let (w,h) = ((cw)*3+2,(ch)*3+2);
     term.clip((tw-w)/2,(th-h)/2,w,h);
+    let locate = Locate::find(&args).and_then(|l| l.within((tw-w)/2,(th-h)/2,w,h));
+    let mut located: Option<Vec<Position>> = None;
     let mut shadowed = false;
     
     for rv in args.iter() {
-      if let Some(_) = rv.downcast_ref::<ShadowedRender>() {
+      if rv.is::<ShadowedRender>() {
         shadowed = true;
       }
     }
@@ -60,6 +131,7 @@ impl<T: GenericGame<P> + TerminalRender,P: Player + TerminalRender> TerminalRend
       term.set_color(fg,bg);
     };
     
+    let highlight = Highlight::find(&args);
     let mut allgood = true;
     
     for i in 0..9 {
@@ -82,17 +154,32 @@ impl<T: GenericGame<P> + TerminalRender,P: Player + TerminalRender> TerminalRend
       term.clip(ox,oy,pcw,pch);
       let mut arg: Vec<Rc<dyn Any>> = vec!();
       if shadowed {arg.push(Rc::new(ShadowedRender))};
+      if let Some(hl) = highlight {
+        arg.push(Rc::new(hl.inside(pos)));
+      }
+      let at = locate.and_then(|l| l.within(ox,oy,pcw,pch));
+      if let Some(at) = at {
+        arg.push(Rc::new(at));
+      }
       let rvs = state.render(term,arg);
+      if at.is_some() {
+        let mut path = vec!(pos);
+        if let Some(Located(inner)) = Located::find(&rvs) {
+          path.extend_from_slice(inner);
+        }
+        located = Some(path);
+      }
       let mut renders = true;
       for rv in rvs.iter() {
-        if let Some(_) = rv.downcast_ref::<DontRenderSelection>() {
+        if rv.is::<DontRenderSelection>() {
           renders = false;
         }
       }
       term.unclip();
-      if self.is_good_cell(pos) && !allgood && renders {
+      let frame = highlight.and_then(|hl| hl.at(pos));
+      if (self.is_good_cell(pos) && !allgood && renders) || frame.is_some() {
         rsel = true;
-        term.set_fg(ColorEnum::LightMagenta);
+        term.set_fg(frame.unwrap_or(ColorEnum::LightMagenta));
         term.set((ox-1) as isize,(oy) as isize,("█\r\n").repeat(pch).as_str());
         term.set((ox+pcw) as isize,(oy) as isize,("█\r\n").repeat(pch).as_str());
       }
@@ -104,6 +191,7 @@ impl<T: GenericGame<P> + TerminalRender,P: Player + TerminalRender> TerminalRend
     if rsel {
       vec.push(Rc::new(DontRenderSelection));
     }
+    vec.extend(Located::give(located));
     vec
   }
 }
@@ -157,7 +245,7 @@ impl<P: Player + TerminalRender> TerminalRender for Entanglement<P>
     let mut shadowed = false;
     
     for rv in args.iter() {
-      if let Some(_) = rv.downcast_ref::<ShadowedRender>() {
+      if rv.is::<ShadowedRender>() {
         shadowed = true;
       }
     }
@@ -187,7 +275,7 @@ impl<P: Player + TerminalRender> TerminalRender for Measurement<P>
     let mut shadowed = false;
     
     for rv in args.iter() {
-      if let Some(_) = rv.downcast_ref::<ShadowedRender>() {
+      if rv.is::<ShadowedRender>() {
         shadowed = true;
       }
     }
@@ -245,10 +333,12 @@ impl<P: Player + TerminalRender> TerminalRender for QuantumTicTacToe<P> {
     let (cw,ch) = (pcw,pch);
     let (w,h) = ((cw)*3+2,(ch)*3+2);
     term.clip((tw-w)/2,(th-h)/2,w,h);
+    let locate = Locate::find(&args).and_then(|l| l.within((tw-w)/2,(th-h)/2,w,h));
+    let mut located: Option<Vec<Position>> = None;
     let mut shadowed = false;
     
     for rv in args.iter() {
-      if let Some(_) = rv.downcast_ref::<ShadowedRender>() {
+      if rv.is::<ShadowedRender>() {
         shadowed = true;
       }
     }
@@ -272,6 +362,12 @@ impl<P: Player + TerminalRender> TerminalRender for QuantumTicTacToe<P> {
       let pos = Position::from_cid(i as u8);
       let state = self.get_classic_mark(pos);
       let (ox,oy) = ((pos.get_x() as usize)*(cw+1),(pos.get_y() as usize)*(ch+1));
+      if let Some(color) = Highlight::find(&args).and_then(|hl| hl.at(pos)) {
+        Highlight::fill(term,ox,oy,pcw,pch,color);
+      }
+      if locate.and_then(|l| l.within(ox,oy,pcw,pch)).is_some() {
+        located = Some(vec!(pos));
+      }
       let (fg,bg) = term.get_color();
       term.clip(ox,oy,pcw,pch);
       
@@ -302,12 +398,106 @@ impl<P: Player + TerminalRender> TerminalRender for QuantumTicTacToe<P> {
     }
     
     term.unclip();
-    vec!()
+    Located::give(located)
+  }
+}
+
+impl<P: Player + TerminalRender> TerminalRender for TicTacToe<P> {
+  fn get_size(&self) -> (usize,usize) {
+    let (mut mw, mut mh) = (0,0);
+    for i in 0..9 {
+      let pos = Position::from_cid(i as u8);
+      let (w,h) = self.get_mark(pos).unwrap_or(self.get_player()).get_size();
+      mw = max(w,mw);
+      mh = max(h,mh);
+    }
+    (mw*3+2,mh*3+2)
+  }
+  fn render(&self, term: &mut TerminalCanvas, args: Vec<Rc<dyn Any>>) -> Vec<Rc<dyn Any>> {
+    let (mw,mh) = self.get_size();
+    let (tw,th) = term.get_size();
+    assert!((mw <= tw) && (mh <= th),"Canvas not big enough");
+    let (cw,ch) = ((tw-2)/3,(th-2)/3);
+    let (w,h) = ((cw)*3+2,(ch)*3+2);
+    term.clip((tw-w)/2,(th-h)/2,w,h);
+    let locate = Locate::find(&args).and_then(|l| l.within((tw-w)/2,(th-h)/2,w,h));
+    let mut located: Option<Vec<Position>> = None;
+    let mut shadowed = false;
+    
+    for rv in args.iter() {
+      if rv.is::<ShadowedRender>() {
+        shadowed = true;
+      }
+    }
+    
+    let cl = format!("{0}│{0}│{0}\r\n",(" ").repeat(cw)).repeat(ch);
+    {
+      let (fg,bg) = term.get_color();
+      term.set_fg(if shadowed { ColorEnum::LightBlack } else { ColorEnum::White });
+      term.set(0,0,
+        format!("{0}{1}{0}",
+          cl,
+          format!("{0}{1}{0}",
+            format!("{0}┼{0}┼{0}\r\n",("─").repeat(cw)),
+            cl
+          )
+        ).as_str()
+      );
+      term.set_color(fg,bg);
+    };
+    for i in 0..9 {
+      let pos = Position::from_cid(i as u8);
+      let (ox,oy) = ((pos.get_x() as usize)*(cw+1),(pos.get_y() as usize)*(ch+1));
+      if let Some(color) = Highlight::find(&args).and_then(|hl| hl.at(pos)) {
+        Highlight::fill(term,ox,oy,cw,ch,color);
+      }
+      if locate.and_then(|l| l.within(ox,oy,cw,ch)).is_some() {
+        located = Some(vec!(pos));
+      }
+      if let Some(mark) = self.get_mark(pos) {
+        let (fg,bg) = term.get_color();
+        term.clip(ox,oy,cw,ch);
+        let mut arg: Vec<Rc<dyn Any>> = vec!();
+        if shadowed {arg.push(Rc::new(ShadowedRender))};
+        mark.render(term,arg);
+        term.unclip();
+        term.set_color(fg,bg);
+      }
+    }
+    
+    term.unclip();
+    Located::give(located)
   }
 }
 
 impl<P: Player> TerminalRender for DummyGame<P> {}
 
+/// Boxed games come out of a `GameSpec`, so they can only be one of the
+/// types it builds.
+fn as_render<P: Player + TerminalRender + 'static>(game: &dyn DynGame<P>) -> &dyn TerminalRender {
+  let game = game.as_any();
+  if let Some(game) = game.downcast_ref::<MixedSuperTicTacToe<P>>() {
+    game
+  } else if let Some(game) = game.downcast_ref::<QuantumTicTacToe<P>>() {
+    game
+  } else if let Some(game) = game.downcast_ref::<TicTacToe<P>>() {
+    game
+  } else if let Some(game) = game.downcast_ref::<DummyGame<P>>() {
+    game
+  } else {
+    panic!("don't know how to render this game");
+  }
+}
+
+impl<P: Player + TerminalRender + 'static> TerminalRender for Box<dyn DynGame<P>> {
+  fn get_size(&self) -> (usize,usize) {
+    as_render(&**self).get_size()
+  }
+  fn render(&self, term: &mut TerminalCanvas, args: Vec<Rc<dyn Any>>) -> Vec<Rc<dyn Any>> {
+    as_render(&**self).render(term,args)
+  }
+}
+
 impl<T:GenericGame<P> + TerminalRender,P: Player + TerminalRender> TerminalRender for SuperDummyGame<T,P> {
   fn get_size(&self) -> (usize,usize) {
     self.get_game_ref().get_size()
@@ -321,7 +511,7 @@ impl<T:GenericGame<P> + TerminalRender,P: Player + TerminalRender> TerminalRende
   }
 }
 
-impl TerminalRender for pl_XO {
+impl TerminalRender for RosterPlayer {
   fn get_size(&self) -> (usize,usize) {
     (1,1)
   }
@@ -329,7 +519,7 @@ impl TerminalRender for pl_XO {
     let mut shadowed = false;
     
     for rv in args.iter() {
-      if let Some(_) = rv.downcast_ref::<ShadowedRender>() {
+      if rv.is::<ShadowedRender>() {
         shadowed = true;
       }
     }
@@ -340,9 +530,10 @@ impl TerminalRender for pl_XO {
     let m = min(w,h);
     let (fg,bg) = term.get_color();
     term.clip(((w-m)/2) as usize,((h-m)/2) as usize,m as usize,m as usize);
-    match self {
-      X => {
-        term.set_fg(if shadowed { ColorEnum::LightBlack } else { ColorEnum::LightRed });
+    let entry = self.entry();
+    term.set_fg(if shadowed { ColorEnum::LightBlack } else { entry.color });
+    match &entry.glyph {
+      Glyph::Cross => {
         match m {
           1 => {
             term.set(0,0,"X");
@@ -378,8 +569,7 @@ impl TerminalRender for pl_XO {
           },
         }
       },
-      O => {
-        term.set_fg(if shadowed { ColorEnum::LightBlack } else { ColorEnum::LightBlue });
+      Glyph::Ring => {
         match m {
           1 => {
             term.set(0,0,"O");
@@ -400,8 +590,7 @@ impl TerminalRender for pl_XO {
           }
         }
       }
-      C => {
-        term.set_fg(if shadowed { ColorEnum::LightBlack } else { ColorEnum::LightGreen });
+      Glyph::Crescent => {
         match m {
           1 => {
             term.set(0,0,"C");
@@ -421,6 +610,23 @@ impl TerminalRender for pl_XO {
           }
         }
       }
+      Glyph::Char(c) => {
+        match m {
+          0 => {
+            panic!("Canvas not big enough");
+          }
+          1 => {
+            term.set(0,0,c.as_str());
+          },
+          _ => {
+            let l = m as usize;
+            term.set(0,0,format!("{}\r\n",c).repeat(l).as_str());
+            term.set((m as isize)-1,0,format!("{}\r\n",c).repeat(l).as_str());
+            term.set(1,0,c.repeat(l-2).as_str());
+            term.set(1,(m as isize)-1,c.repeat(l-2).as_str());
+          }
+        }
+      }
     };
     term.unclip();
     term.set_color(fg,bg);
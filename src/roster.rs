@@ -0,0 +1,250 @@
+use crate::tic_tac_toe::Player;
+use crate::terminal_render::ColorEnum;
+use std::fmt;
+
+/// How a player's marks are drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Glyph {
+  Cross,
+  Ring,
+  Crescent,
+  /// Any single character, drawn as a frame when there's room.
+  Char(String),
+}
+
+impl Glyph {
+  pub fn parse(s: &str) -> Result<Glyph,String> {
+    match s {
+      "cross" => Ok(Glyph::Cross),
+      "ring" => Ok(Glyph::Ring),
+      "crescent" => Ok(Glyph::Crescent),
+      s if s.chars().count() == 1 => Ok(Glyph::Char(String::from(s))),
+      s => Err(format!("unknown glyph '{}', expected cross, ring, crescent or a single character", s)),
+    }
+  }
+}
+
+pub fn parse_color(s: &str) -> Result<ColorEnum,String> {
+  Ok(match s.to_ascii_lowercase().replace("-","").replace("_","").as_str() {
+    "black" => ColorEnum::Black,
+    "blue" => ColorEnum::Blue,
+    "cyan" => ColorEnum::Cyan,
+    "green" => ColorEnum::Green,
+    "magenta" => ColorEnum::Magenta,
+    "red" => ColorEnum::Red,
+    "white" => ColorEnum::White,
+    "yellow" => ColorEnum::Yellow,
+    "lightblack" | "gray" | "grey" => ColorEnum::LightBlack,
+    "lightblue" => ColorEnum::LightBlue,
+    "lightcyan" => ColorEnum::LightCyan,
+    "lightgreen" => ColorEnum::LightGreen,
+    "lightmagenta" => ColorEnum::LightMagenta,
+    "lightred" => ColorEnum::LightRed,
+    "lightwhite" => ColorEnum::LightWhite,
+    "lightyellow" => ColorEnum::LightYellow,
+    _ => return Err(format!("unknown colour '{}'", s)),
+  })
+}
+
+#[derive(Clone, Debug)]
+pub struct RosterEntry {
+  pub name: String,
+  pub glyph: Glyph,
+  pub color: ColorEnum,
+}
+
+impl RosterEntry {
+  /// Parses `NAME[:GLYPH[:COLOUR]]`. A missing glyph is the first letter of
+  /// the name.
+  pub fn parse(s: &str, default_color: ColorEnum) -> Result<RosterEntry,String> {
+    let mut parts = s.split(':');
+    let name = parts.next().unwrap_or("");
+    if name.is_empty() {
+      return Err(format!("player '{}' has no name", s));
+    }
+    let glyph = match parts.next() {
+      Some(g) => Glyph::parse(g)?,
+      None => Glyph::Char(name.chars().take(1).collect()),
+    };
+    let color = match parts.next() {
+      Some(c) => parse_color(c)?,
+      None => default_color,
+    };
+    if parts.next().is_some() {
+      return Err(format!("player '{}' has too many fields", s));
+    }
+    Ok(RosterEntry{name: String::from(name), glyph, color})
+  }
+}
+
+/// The players of a game, the order they move in and who plays together.
+#[derive(Debug)]
+pub struct Roster {
+  entries: Vec<RosterEntry>,
+  order: Vec<usize>,
+  teams: Vec<Vec<usize>>,
+}
+
+impl Roster {
+  fn find_id(entries: &[RosterEntry], name: &str) -> Result<usize,String> {
+    entries.iter().position(|e| e.name == name)
+      .ok_or_else(|| format!("unknown player '{}'", name))
+  }
+
+  /// `order` lists player names in turn order. Empty means the order of
+  /// `entries`. Each of `teams` lists the names of one team; players left
+  /// out play alone.
+  pub fn new(entries: Vec<RosterEntry>, order: Vec<String>, teams: Vec<Vec<String>>) -> Result<Roster,String> {
+    if entries.len() < 2 {
+      return Err(String::from("a game needs at least 2 players"));
+    }
+    for (i,e) in entries.iter().enumerate() {
+      if entries[..i].iter().any(|o| o.name == e.name) {
+        return Err(format!("player '{}' is listed twice", e.name));
+      }
+    }
+    let order = if order.is_empty() {
+      (0..entries.len()).collect()
+    } else {
+      let mut ids = vec!();
+      for name in order.iter() {
+        let id = Self::find_id(&entries, name).map_err(|e| format!("turn order names {}", e))?;
+        if ids.contains(&id) {
+          return Err(format!("turn order names '{}' twice", name));
+        }
+        ids.push(id);
+      }
+      if ids.len() != entries.len() {
+        return Err(String::from("turn order must name every player"));
+      }
+      ids
+    };
+    let mut team_ids: Vec<Vec<usize>> = vec!();
+    for team in teams.iter() {
+      let mut ids = vec!();
+      for name in team.iter() {
+        let id = Self::find_id(&entries, name).map_err(|e| format!("team names {}", e))?;
+        if team_ids.iter().any(|t| t.contains(&id)) || ids.contains(&id) {
+          return Err(format!("player '{}' is in more than one team", name));
+        }
+        ids.push(id);
+      }
+      team_ids.push(ids);
+    }
+    Ok(Roster{entries, order, teams: team_ids})
+  }
+
+  /// The classic three: red cross, blue ring and green crescent.
+  pub fn default_entries() -> Vec<RosterEntry> {
+    vec!(
+      RosterEntry{name: String::from("X"), glyph: Glyph::Cross, color: ColorEnum::LightRed},
+      RosterEntry{name: String::from("O"), glyph: Glyph::Ring, color: ColorEnum::LightBlue},
+      RosterEntry{name: String::from("C"), glyph: Glyph::Crescent, color: ColorEnum::LightGreen},
+    )
+  }
+
+  /// Rosters live as long as the program so players can stay `Copy`.
+  pub fn leak(self) -> &'static Roster {
+    Box::leak(Box::new(self))
+  }
+
+  pub fn first(&'static self) -> RosterPlayer {
+    RosterPlayer{roster: self, id: self.order[0]}
+  }
+
+  /// Every player, in turn order.
+  pub fn players(&'static self) -> Vec<RosterPlayer> {
+    self.order.iter().map(|&id| RosterPlayer{roster: self, id}).collect()
+  }
+
+  /// Names of the players in turn order.
+  pub fn names(&self) -> Vec<String> {
+    self.order.iter().map(|&id| self.entries[id].name.clone()).collect()
+  }
+
+  /// Every team of more than one player, as names joined with `+`, sorted
+  /// so the same teams always come out the same.
+  pub fn team_names(&self) -> Vec<String> {
+    let mut teams: Vec<String> = self.teams.iter().filter(|t| t.len() > 1).map(|t| {
+      let mut names: Vec<&str> = t.iter().map(|&id| self.entries[id].name.as_str()).collect();
+      names.sort();
+      names.join("+")
+    }).collect();
+    teams.sort();
+    teams
+  }
+
+  pub fn find(&'static self, name: &str) -> Option<RosterPlayer> {
+    self.entries.iter().position(|e| e.name == name).map(|id| RosterPlayer{roster: self, id})
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+}
+
+#[derive(Copy, Clone)]
+pub struct RosterPlayer {
+  roster: &'static Roster,
+  id: usize,
+}
+
+impl RosterPlayer {
+  pub fn entry(&self) -> &'static RosterEntry {
+    &self.roster.entries[self.id]
+  }
+  pub fn name(&self) -> &'static str {
+    self.entry().name.as_str()
+  }
+  /// Position of the player in the roster, not in the turn order.
+  pub fn get_id(&self) -> usize {
+    self.id
+  }
+  fn team_id(&self) -> Option<usize> {
+    self.roster.teams.iter().position(|t| t.contains(&self.id))
+  }
+  /// Names of the team members joined with `+`, or just the player's name
+  /// when playing alone.
+  pub fn team_name(&self) -> String {
+    match self.team_id() {
+      Some(t) => {
+        let names: Vec<&str> = self.roster.teams[t].iter().map(|&id| self.roster.entries[id].name.as_str()).collect();
+        names.join("+")
+      },
+      None => String::from(self.name()),
+    }
+  }
+  /// One player of every team playing against this one, in turn order.
+  pub fn opponents(&self) -> Vec<RosterPlayer> {
+    let mut opponents: Vec<RosterPlayer> = vec!();
+    for p in self.roster.players() {
+      if !p.is_teammate(self) && !opponents.iter().any(|o| o.is_teammate(&p)) {
+        opponents.push(p);
+      }
+    }
+    opponents
+  }
+}
+
+impl Player for RosterPlayer {
+  fn next_player(&self) -> Self {
+    let order = &self.roster.order;
+    let i = order.iter().position(|&id| id == self.id).expect("player is not in its roster");
+    RosterPlayer{roster: self.roster, id: order[(i+1)%order.len()]}
+  }
+  fn get_uuid(&self) -> String {
+    format!("roster_{}", self.id)
+  }
+  fn get_team(&self) -> String {
+    match self.team_id() {
+      Some(t) => format!("roster_team_{}", t),
+      None => self.get_uuid(),
+    }
+  }
+}
+
+impl fmt::Debug for RosterPlayer {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
@@ -0,0 +1,174 @@
+use crate::tic_tac_toe::*;
+use crate::roster::RosterPlayer;
+use crate::analysis::Analysis;
+use crate::Game;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::mpsc::{channel,Sender,Receiver,TryRecvError};
+use std::thread;
+use std::time::Duration;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// How long pondering goes between looks at the mailbox.
+const PONDER_SLICE: Duration = Duration::from_millis(50);
+
+enum Command {
+  Choose(Game,Option<Duration>),
+  /// The game before the move, and the move's index in its valid moves.
+  Advance(Game,usize),
+  /// A new game, with a new seed.
+  Reset(u64),
+  /// The game went back to an earlier position, like after an undo.
+  Forget,
+}
+
+/// A bot playing one seat on a thread of its own, so it can ponder while
+/// the others move. Moves can't cross threads, so they go back and forth
+/// as indices into `get_valid_moves`. All the bot's luck comes from its
+/// seed, so without pondering and with a playout budget it plays the same
+/// game again from the same seed.
+pub struct BotSeat {
+  tx: Sender<Command>,
+  rx: Receiver<(Option<usize>,Option<Analysis>)>,
+}
+
+impl BotSeat {
+  pub fn spawn(player: RosterPlayer, config: BotConfig, threads: usize, book: Option<Arc<OpeningBook>>, ponder: bool, seed: u64) -> BotSeat {
+    let (tx,commands) = channel::<Command>();
+    let (replies,rx) = channel();
+    thread::spawn(move || {
+      let mut rng = StdRng::seed_from_u64(seed);
+      let mut bot: Bot<Game,RosterPlayer> = Bot::new(config,threads);
+      bot.book = book;
+      let mut pondering: Option<Game> = None;
+      loop {
+        let command = match &pondering {
+          Some(game) => match commands.try_recv() {
+            Ok(command) => command,
+            Err(TryRecvError::Empty) => {
+              if !bot.ponder(game,player,PONDER_SLICE,&mut rng) {
+                pondering = None;
+              }
+              continue;
+            },
+            Err(TryRecvError::Disconnected) => break,
+          },
+          None => match commands.recv() {
+            Ok(command) => command,
+            Err(_) => break,
+          },
+        };
+        pondering = None;
+        match command {
+          Command::Choose(game,limit) => {
+            let moves = game.get_valid_moves();
+            let index = bot.choose(&game,limit,&mut rng)
+              .and_then(|m| moves.iter().position(|o| game.is_same_move(o,&m)));
+            let stats = bot.analysis();
+            let analysis = if !stats.is_empty() {Some(Analysis::new(player,stats))} else {None};
+            if replies.send((index,analysis)).is_err() {
+              break;
+            }
+          },
+          Command::Advance(mut game,index) => {
+            let m = Rc::clone(&game.get_valid_moves()[index]);
+            bot.advance(&game,&m);
+            if let GameResult::Incomplete = game.mov(m).result {
+              if ponder && game.get_player().get_id() != player.get_id() {
+                pondering = Some(game);
+              }
+            }
+          },
+          Command::Reset(seed) => {
+            bot.reset();
+            rng = StdRng::seed_from_u64(seed);
+          },
+          Command::Forget => {
+            bot.reset();
+            bot.leave_book();
+          },
+        }
+      }
+    });
+    BotSeat{tx,rx}
+  }
+
+  /// The index of the bot's move in `game`, and what it thought of the
+  /// others. Blocks until it has decided.
+  pub fn choose(&self, game: &Game, limit: Option<Duration>) -> (Option<usize>,Option<Analysis>) {
+    self.ask(game,limit);
+    self.rx.recv().expect("bot thread died")
+  }
+
+  /// Starts the bot deciding on a move in `game`, for `answer` to pick up.
+  pub fn ask(&self, game: &Game, limit: Option<Duration>) {
+    self.tx.send(Command::Choose(game.clone(),limit)).expect("bot thread died");
+  }
+
+  /// What the bot decided since `ask`, if it has yet.
+  pub fn answer(&self) -> Option<(Option<usize>,Option<Analysis>)> {
+    self.rx.try_recv().ok()
+  }
+
+  /// Tells the bot the move at `index` was played in `before`.
+  pub fn advance(&self, before: &Game, index: usize) {
+    self.tx.send(Command::Advance(before.clone(),index)).expect("bot thread died");
+  }
+
+  pub fn reset(&self, seed: u64) {
+    self.tx.send(Command::Reset(seed)).expect("bot thread died");
+  }
+
+  /// Makes the bot drop what it knew of the game, since it went back.
+  pub fn forget(&self) {
+    self.tx.send(Command::Forget).expect("bot thread died");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::roster::Roster;
+
+  /// The first moves two seats play against each other, by index.
+  fn seat_game(seats: &[BotSeat], roster: &'static Roster) -> Vec<usize> {
+    let mut game = crate::new_game(&"S(T)".parse().unwrap(), roster, &None).unwrap();
+    let mut moves = vec!();
+    for _ in 0..12 {
+      let player = game.get_player();
+      let index = seats[player.get_id()].choose(&game, None).0.expect("the bot found no move");
+      for seat in seats.iter() {
+        seat.advance(&game, index);
+      }
+      moves.push(index);
+      let m = Rc::clone(&game.get_valid_moves()[index]);
+      if let GameResult::Incomplete = game.mov(m).result {} else {
+        break;
+      }
+    }
+    moves
+  }
+
+  #[test]
+  fn same_seeds_same_game() {
+    let mut entries = Roster::default_entries();
+    entries.truncate(2);
+    let roster = Roster::new(entries, vec!(), vec!()).unwrap().leak();
+    let config = BotConfig::parse("mcts,playouts=50", |_: &str| None).unwrap();
+    let spawn = |seeds: [u64; 2]| -> Vec<BotSeat> {
+      roster.players().iter().zip(seeds.iter()).map(|(&p,&seed)| {
+        BotSeat::spawn(p, config.clone(), 2, None, false, seed)
+      }).collect()
+    };
+    let seats = spawn([1, 2]);
+    let first = seat_game(&seats, roster);
+    assert_eq!(seat_game(&spawn([1, 2]), roster), first);
+    // a new game from the same seeds plays out the same too
+    for (seat,seed) in seats.iter().zip([1, 2].iter()) {
+      seat.reset(*seed);
+    }
+    assert_eq!(seat_game(&seats, roster), first);
+    assert!(seat_game(&spawn([3, 4]), roster) != first);
+  }
+}
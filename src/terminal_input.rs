@@ -1,78 +1,506 @@
 use crate::tic_tac_toe::*;
+use crate::terminal_render::ColorEnum;
+use crate::rendering::Highlight;
+use std::rc::Rc;
+use std::any::Any;
 
-pub trait TerminalInput {
-  fn input(&mut self, args: Vec<Rc<dyn Any>>) -> Vec<Rc<dyn Any>>;
-}
+/// The cursor's colour, on a cell or around a board.
+const CURSOR_COLOR: ColorEnum = ColorEnum::LightBlue;
+/// Cells already picked for a move that takes more than one.
+const PICKED_COLOR: ColorEnum = ColorEnum::Blue;
 
-pub struct InputHandle {
-  store: Vec<Rc<dyn Any>>,
-}
-
-#[derive(Copy, Clone, Debug)]
+/// What the player asked the cursor to do.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Control {
   Left,
   Right,
   Up,
   Down,
-  Push
+  /// Go into the board under the cursor, or pick the cell.
+  Push,
+  /// Back out to the board around this one.
+  Pop,
 }
 
-struct ControlBackpropagation {
-  con: Option<Control>,
-  pos: Position,
-  rec: Option<Box<ControlBackpropagation>>
+/// What a board did with a control, for the board around it.
+pub enum ControlBackpropagation {
+  /// The cursor moved, or a cell was picked for a move that needs more.
+  Handled,
+  /// The move the player just finished.
+  Move(Rc<dyn Any>),
+  /// The cursor should go back to the board around this one.
+  Ascend,
+  /// Nothing to do, like an arrow with no cell that way.
+  Ignored,
 }
 
-struct Substore([Vec<Rc<dyn Any>>;9]);
+/// Where the cursor is in one board and, once it has gone into one of its
+/// cells, in the board there.
+#[derive(Clone, Debug, Default)]
+pub struct Cursor {
+  pub pos: Option<Position>,
+  pub inner: Option<Box<Cursor>>,
+  /// Cells picked in this board so far, like the first cell of a spooky
+  /// mark.
+  pub picked: Vec<Position>,
+}
 
-struct PositionStore(Position);
+impl Cursor {
+  /// Colours the cursor and picked cells, by their paths from this board.
+  fn highlight(&self, path: &mut Vec<Position>, cells: &mut Vec<(Vec<Position>,ColorEnum)>) {
+    if let Some(pos) = self.pos {
+      path.push(pos);
+      match &self.inner {
+        Some(inner) => inner.highlight(path,cells),
+        None => cells.push((path.clone(),CURSOR_COLOR)),
+      }
+      path.pop();
+    }
+    for pos in self.picked.iter() {
+      let mut picked = path.clone();
+      picked.push(*pos);
+      cells.push((picked,PICKED_COLOR));
+    }
+  }
+}
 
-struct HighestLevel;
+/// Boards a player can move the cursor around in.
+pub trait TerminalInput {
+  /// Cells the cursor may stop on in this board.
+  fn targets(&self, cursor: &Cursor) -> Vec<Position>;
+  /// Does what `control` says with `cursor` in this board.
+  fn input(&self, cursor: &mut Cursor, control: Control) -> ControlBackpropagation;
+  /// Puts the cursor back on a cell it may stop on, after the position
+  /// changed under it.
+  fn settle(&self, cursor: &mut Cursor) {
+    let targets = self.targets(cursor);
+    if !cursor.pos.map(|pos| targets.contains(&pos)).unwrap_or(false) {
+      cursor.pos = targets.first().cloned();
+      cursor.inner = None;
+    }
+  }
+  /// Puts the cursor on the cell at `path`, like where the player clicked,
+  /// if it may stop there and in every board on the way.
+  fn point(&self, cursor: &mut Cursor, path: &[Position]) -> bool {
+    match path {
+      [pos] if self.targets(cursor).contains(pos) => {
+        cursor.pos = Some(*pos);
+        true
+      },
+      _ => false,
+    }
+  }
+}
 
-impl InputHandle {
-  fn input<T: GenericGame<P> + TerminalInput, P: Player>(&mut self, game: &mut T, args: Vec<Rc<dyn Any>>) -> Vec<Rc<dyn Any>> {
-    let mut arg: Vec<Rc<dyn Any>> = vec!(Rc::new(HighestLevel));
-    arg.extend(args);
-    arg.extend(self.store);
-    self.store = game.input(arg);
-  }
-}
-
-impl<T: GenericGame<P>, P: Player> TerminalInput for SuperTicTacToe<T,P> {
-  fn input(&mut self, args: Vec<Rc<dyn Any>>) -> Vec<Rc<dyn Any>> {
-    let mut control: Option<Control> = None;
-    let mut cpos = Position::default();
-    let mut substore: [Vec<Rc<dyn Any>>] = [vec!();9];
-    let mut store: Vec<Rc<dyn Any>> = vec!();
-    
-    for rv in args.iter() {
-      if let Some(c) = rv.downcast_ref::<Control>() {
-        control = Some(c);
-      }
-      if let Some(PositionStore(pos)) = rv.downcast_ref::<PositionStore>() {
-        cpos = pos;
-      }
-      if let Some(subs) = rv.downcast_ref::<Substore>() {
-        substore = subs;
-      }
+/// The cell nearest to `from` in the direction of `control`, keeping to
+/// the same row or column when it can.
+fn step(targets: &[Position], from: Position, control: Control) -> Option<Position> {
+  let (fx,fy) = (from.get_x() as i32,from.get_y() as i32);
+  let mut best: Option<(i32,Position)> = None;
+  for pos in targets.iter() {
+    let (dx,dy) = (pos.get_x() as i32-fx,pos.get_y() as i32-fy);
+    let (ahead,aside) = match control {
+      Control::Left => (-dx,dy),
+      Control::Right => (dx,dy),
+      Control::Up => (-dy,dx),
+      Control::Down => (dy,dx),
+      _ => return None,
+    };
+    if ahead <= 0 {
+      continue;
     }
-    
-    let arg: Vec<Rc<dyn Any>> = vec!();
-    
-    for rv in substore[cpos.get_cid() as usize].iter() {
-      if let Some(c) = rv.downcast_ref::<Position> {
-        
+    let cost = ahead+2*aside.abs();
+    if best.map(|(c,_)| cost < c).unwrap_or(true) {
+      best = Some((cost,*pos));
+    }
+  }
+  best.map(|(_,pos)| pos)
+}
+
+/// Arrow keys and backing out, the same on every board.
+fn navigate(targets: Vec<Position>, cursor: &mut Cursor, control: Control) -> ControlBackpropagation {
+  match control {
+    Control::Pop if !cursor.picked.is_empty() => {
+      cursor.picked.clear();
+      ControlBackpropagation::Handled
+    },
+    Control::Pop => ControlBackpropagation::Ascend,
+    Control::Push => ControlBackpropagation::Ignored,
+    _ => {
+      let next = match cursor.pos {
+        Some(pos) => step(&targets,pos,control),
+        None => targets.first().cloned(),
+      };
+      match next {
+        Some(pos) => {
+          cursor.pos = Some(pos);
+          ControlBackpropagation::Handled
+        },
+        None => ControlBackpropagation::Ignored,
       }
+    },
+  }
+}
+
+impl<T: GenericGame<P> + TerminalInput, P: Player> TerminalInput for SuperTicTacToe<T,P> {
+  fn targets(&self, _cursor: &Cursor) -> Vec<Position> {
+    (0..9).map(Position::from_cid).filter(|pos| {
+      self.is_good_cell(*pos) && self.get_cell_ref(*pos).get_game_ref().is_some()
+    }).collect()
+  }
+  fn input(&self, cursor: &mut Cursor, control: Control) -> ControlBackpropagation {
+    self.settle(cursor);
+    let pos = match cursor.pos {
+      Some(pos) => pos,
+      None => return ControlBackpropagation::Ignored,
+    };
+    let game = self.get_cell_ref(pos).get_game_ref().expect("targets only lists boards that are still playing");
+    if let Some(inner) = &mut cursor.inner {
+      return match game.input(inner,control) {
+        ControlBackpropagation::Move(submove) => ControlBackpropagation::Move(Rc::new(SuperTicTacToeMove{pos, submove})),
+        ControlBackpropagation::Ascend => {
+          cursor.inner = None;
+          ControlBackpropagation::Handled
+        },
+        other => other,
+      };
+    }
+    if control == Control::Push {
+      let mut inner = Cursor::default();
+      game.settle(&mut inner);
+      cursor.inner = Some(Box::new(inner));
+      return ControlBackpropagation::Handled;
+    }
+    navigate(self.targets(cursor),cursor,control)
+  }
+  fn settle(&self, cursor: &mut Cursor) {
+    let targets = self.targets(cursor);
+    if !cursor.pos.map(|pos| targets.contains(&pos)).unwrap_or(false) {
+      cursor.pos = targets.first().cloned();
+      cursor.inner = None;
+    }
+    if let (Some(pos),Some(inner)) = (cursor.pos,&mut cursor.inner) {
+      self.get_cell_ref(pos).get_game_ref().expect("the cursor was just settled on a board that is still playing").settle(inner);
+    }
+  }
+  fn point(&self, cursor: &mut Cursor, path: &[Position]) -> bool {
+    let pos = match path.first() {
+      Some(pos) if self.targets(cursor).contains(pos) => *pos,
+      _ => return false,
+    };
+    let mut inner = match cursor.inner.take() {
+      Some(inner) if cursor.pos == Some(pos) => *inner,
+      _ => Cursor::default(),
+    };
+    cursor.pos = Some(pos);
+    if path.len() == 1 {
+      return true;
+    }
+    let game = self.get_cell_ref(pos).get_game_ref().expect("only boards that are still playing can be pointed into");
+    if game.point(&mut inner,&path[1..]) {
+      cursor.inner = Some(Box::new(inner));
+      true
+    } else {
+      false
+    }
+  }
+}
+
+impl<P: Player> TerminalInput for TicTacToe<P> {
+  fn targets(&self, _cursor: &Cursor) -> Vec<Position> {
+    self.get_valid_moves().iter().filter_map(|m| m.downcast_ref::<TicTacToeMove>().map(|m| m.pos)).collect()
+  }
+  fn input(&self, cursor: &mut Cursor, control: Control) -> ControlBackpropagation {
+    self.settle(cursor);
+    match (control,cursor.pos) {
+      (Control::Push,Some(pos)) => {
+        let m = Rc::new(TicTacToeMove{pos});
+        if self.is_valid_move(m.clone()) {
+          ControlBackpropagation::Move(m)
+        } else {
+          ControlBackpropagation::Ignored
+        }
+      },
+      _ => navigate(self.targets(cursor),cursor,control),
     }
-    
-    let sgr = self.get_cell_ref(cpos).input();
-    
-    for rv in sgr.iter() {
-      if let Some(c) = rv.downcast_ref::<ControlBackpropagation>() {
-        
+  }
+}
+
+/// A spooky mark takes two cells, picked one after the other; collapsing
+/// a cycle is picking which of the last mark's two cells it ends up in.
+impl<P: Player> TerminalInput for QuantumTicTacToe<P> {
+  fn targets(&self, cursor: &Cursor) -> Vec<Position> {
+    if let Some(e) = self.get_collapse() {
+      return vec!(e.p1,e.p2);
+    }
+    let mut targets = vec!();
+    for m in self.get_valid_moves().iter() {
+      if let Some(m) = m.downcast_ref::<QuantumTicTacToeMoveEnt>() {
+        let (p1,p2) = m.get_cells();
+        let cells = match cursor.picked.first() {
+          Some(&first) if first == p1 => vec!(p1,p2),
+          Some(&first) if first == p2 => vec!(p2,p1),
+          Some(_) => vec!(),
+          None => vec!(p1,p2),
+        };
+        for pos in cells {
+          if !targets.contains(&pos) {
+            targets.push(pos);
+          }
+        }
       }
     }
-    
-    substore[cpos.get_cid() as usize] = sgr;
+    targets.sort_by_key(|pos| pos.get_cid());
+    targets
+  }
+  fn input(&self, cursor: &mut Cursor, control: Control) -> ControlBackpropagation {
+    self.settle(cursor);
+    let pos = match (control,cursor.pos) {
+      (Control::Push,Some(pos)) => pos,
+      _ => return navigate(self.targets(cursor),cursor,control),
+    };
+    let moves = self.get_valid_moves();
+    if let Some(e) = self.get_collapse() {
+      let first = pos == e.p1;
+      return match moves.iter().find(|m| m.downcast_ref::<QuantumTicTacToeMoveMeas>().map(|m| m.is_first() == first).unwrap_or(false)) {
+        Some(m) => ControlBackpropagation::Move(Rc::clone(m)),
+        None => ControlBackpropagation::Ignored,
+      };
+    }
+    match cursor.picked.first().cloned() {
+      Some(first) if first == pos => {
+        cursor.picked.clear();
+        ControlBackpropagation::Handled
+      },
+      Some(first) => {
+        let m = moves.iter().find(|m| m.downcast_ref::<QuantumTicTacToeMoveEnt>().map(|m| {
+          let (p1,p2) = m.get_cells();
+          (p1 == first && p2 == pos) || (p1 == pos && p2 == first)
+        }).unwrap_or(false));
+        match m {
+          Some(m) => {
+            cursor.picked.clear();
+            ControlBackpropagation::Move(Rc::clone(m))
+          },
+          None => ControlBackpropagation::Ignored,
+        }
+      },
+      None => {
+        cursor.picked.push(pos);
+        ControlBackpropagation::Handled
+      },
+    }
+  }
+  fn settle(&self, cursor: &mut Cursor) {
+    if self.get_collapse().is_some() {
+      cursor.picked.clear();
+    }
+    let targets = self.targets(cursor);
+    if !cursor.pos.map(|pos| targets.contains(&pos)).unwrap_or(false) {
+      cursor.pos = targets.first().cloned();
+    }
+  }
+}
+
+/// Won by whoever moves, so any cell will do.
+impl<P: Player> TerminalInput for DummyGame<P> {
+  fn targets(&self, _cursor: &Cursor) -> Vec<Position> {
+    vec!(Position::from_cid(4))
+  }
+  fn input(&self, cursor: &mut Cursor, control: Control) -> ControlBackpropagation {
+    self.settle(cursor);
+    match control {
+      Control::Push => ControlBackpropagation::Move(Rc::clone(&self.get_valid_moves()[0])),
+      _ => navigate(self.targets(cursor),cursor,control),
+    }
+  }
+}
+
+fn as_input<P: Player + 'static>(game: &dyn DynGame<P>) -> &dyn TerminalInput {
+  let game = game.as_any();
+  if let Some(game) = game.downcast_ref::<MixedSuperTicTacToe<P>>() {
+    game
+  } else if let Some(game) = game.downcast_ref::<QuantumTicTacToe<P>>() {
+    game
+  } else if let Some(game) = game.downcast_ref::<TicTacToe<P>>() {
+    game
+  } else if let Some(game) = game.downcast_ref::<DummyGame<P>>() {
+    game
+  } else {
+    panic!("don't know how to take input for this game");
+  }
+}
+
+impl<P: Player + 'static> TerminalInput for Box<dyn DynGame<P>> {
+  fn targets(&self, cursor: &Cursor) -> Vec<Position> {
+    as_input(&**self).targets(cursor)
+  }
+  fn input(&self, cursor: &mut Cursor, control: Control) -> ControlBackpropagation {
+    as_input(&**self).input(cursor,control)
+  }
+  fn settle(&self, cursor: &mut Cursor) {
+    as_input(&**self).settle(cursor)
+  }
+  fn point(&self, cursor: &mut Cursor, path: &[Position]) -> bool {
+    as_input(&**self).point(cursor,path)
+  }
+}
+
+impl<T: GenericGame<P> + TerminalInput, P: Player> TerminalInput for SuperDummyGame<T,P> {
+  fn targets(&self, cursor: &Cursor) -> Vec<Position> {
+    self.get_game_ref().get_game_ref().map(|game| game.targets(cursor)).unwrap_or(vec!())
+  }
+  fn input(&self, cursor: &mut Cursor, control: Control) -> ControlBackpropagation {
+    match self.get_game_ref().get_game_ref() {
+      Some(game) => game.input(cursor,control),
+      None => ControlBackpropagation::Ignored,
+    }
+  }
+  fn settle(&self, cursor: &mut Cursor) {
+    if let Some(game) = self.get_game_ref().get_game_ref() {
+      game.settle(cursor);
+    }
+  }
+  fn point(&self, cursor: &mut Cursor, path: &[Position]) -> bool {
+    self.get_game_ref().get_game_ref().map(|game| game.point(cursor,path)).unwrap_or(false)
+  }
+}
+
+/// The cursor of whoever is making a move, kept between key presses.
+#[derive(Default)]
+pub struct InputHandle {
+  cursor: Cursor,
+}
+
+impl InputHandle {
+  /// Applies `control` in `game`, giving back the move once the player
+  /// has finished one.
+  pub fn input<T: TerminalInput>(&mut self, game: &T, control: Control) -> Option<Rc<dyn Any>> {
+    match game.input(&mut self.cursor,control) {
+      ControlBackpropagation::Move(m) => {
+        self.cursor = Cursor::default();
+        Some(m)
+      },
+      _ => None,
+    }
   }
-}
\ No newline at end of file
+
+  /// Plays or picks the cell at `path` in `game`, or goes into the board
+  /// there. The cursor stays where it was when the cell can't be played.
+  pub fn click<T: TerminalInput>(&mut self, game: &T, path: &[Position]) -> Result<Option<Rc<dyn Any>>,String> {
+    let mut cursor = self.cursor.clone();
+    game.settle(&mut cursor);
+    if !game.point(&mut cursor,path) {
+      return Err(String::from("Can't play there"));
+    }
+    match game.input(&mut cursor,Control::Push) {
+      ControlBackpropagation::Move(m) => {
+        self.cursor = Cursor::default();
+        Ok(Some(m))
+      },
+      ControlBackpropagation::Handled => {
+        self.cursor = cursor;
+        Ok(None)
+      },
+      _ => Err(String::from("Can't play there")),
+    }
+  }
+
+  /// The cursor as it should be drawn in `game`.
+  pub fn highlight<T: TerminalInput>(&mut self, game: &T) -> Highlight {
+    game.settle(&mut self.cursor);
+    let mut cells = vec!();
+    self.cursor.highlight(&mut vec!(),&mut cells);
+    Highlight(cells)
+  }
+
+  pub fn reset(&mut self) {
+    self.cursor = Cursor::default();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Copy, Clone, Debug, PartialEq)]
+  enum P {X, O}
+
+  impl Player for P {
+    fn next_player(&self) -> Self {
+      match self {P::X => P::O, P::O => P::X}
+    }
+    fn get_uuid(&self) -> String {
+      format!("{:?}", self)
+    }
+  }
+
+  fn cells(cids: &[u8]) -> Vec<Position> {
+    cids.iter().map(|&cid| Position::from_cid(cid)).collect()
+  }
+
+  #[test]
+  fn arrows_step_to_the_nearest_cell() {
+    let all = cells(&[0,1,2,3,4,5,6,7,8]);
+    let centre = Position::from_cid(4);
+    assert_eq!(step(&all,centre,Control::Left), Some(Position::from_cid(3)));
+    assert_eq!(step(&all,centre,Control::Right), Some(Position::from_cid(5)));
+    assert_eq!(step(&all,centre,Control::Up), Some(Position::from_cid(1)));
+    assert_eq!(step(&all,centre,Control::Down), Some(Position::from_cid(7)));
+    assert_eq!(step(&all,Position::from_cid(0),Control::Left), None);
+    // over the gaps, keeping to the row when there's a cell in it
+    let some = cells(&[2,5,6]);
+    assert_eq!(step(&some,Position::from_cid(0),Control::Right), Some(Position::from_cid(2)));
+    assert_eq!(step(&some,Position::from_cid(0),Control::Down), Some(Position::from_cid(6)));
+    assert_eq!(step(&some,Position::from_cid(3),Control::Right), Some(Position::from_cid(5)));
+
+    let mut cursor = Cursor::default();
+    assert!(matches!(navigate(all.clone(),&mut cursor,Control::Down), ControlBackpropagation::Handled));
+    assert_eq!(cursor.pos, Some(Position::from_cid(0)));
+    assert!(matches!(navigate(all.clone(),&mut cursor,Control::Up), ControlBackpropagation::Ignored));
+    assert!(matches!(navigate(all,&mut cursor,Control::Pop), ControlBackpropagation::Ascend));
+  }
+
+  #[test]
+  fn spooky_marks_take_two_cells() {
+    let game = QuantumTicTacToe::create(P::X, Position::from_cid(0));
+    let mut handle = InputHandle::default();
+    assert!(handle.input(&game,Control::Push).is_none());
+    assert_eq!(handle.cursor.picked, cells(&[0]));
+    // backing out drops the first cell instead of leaving the board
+    assert!(matches!(game.input(&mut handle.cursor,Control::Pop), ControlBackpropagation::Handled));
+    assert!(handle.cursor.picked.is_empty());
+    assert!(handle.input(&game,Control::Push).is_none());
+    assert!(handle.input(&game,Control::Right).is_none());
+    let m = handle.input(&game,Control::Push).expect("two cells make a move");
+    let (p1,p2) = m.downcast_ref::<QuantumTicTacToeMoveEnt>().unwrap().get_cells();
+    assert_eq!(vec!(p1.get_cid(),p2.get_cid()), vec!(0,1));
+    assert!(handle.cursor.picked.is_empty());
+
+    assert!(handle.click(&game,&cells(&[8])).unwrap().is_none());
+    let m = handle.click(&game,&cells(&[2])).unwrap().expect("two cells make a move");
+    let (p1,p2) = m.downcast_ref::<QuantumTicTacToeMoveEnt>().unwrap().get_cells();
+    assert_eq!(vec!(p1.get_cid(),p2.get_cid()), vec!(2,8));
+  }
+
+  #[test]
+  fn clicks_only_play_what_can_be_played() {
+    let mut game = TicTacToe::create(P::X, Position::from_cid(0));
+    game.mov(Rc::new(TicTacToeMove{pos: Position::from_cid(4)}));
+    let mut handle = InputHandle::default();
+    handle.input(&game,Control::Right);
+    let before = handle.cursor.pos;
+    assert_eq!(handle.click(&game,&cells(&[4])).err(), Some(String::from("Can't play there")));
+    assert_eq!(handle.click(&game,&cells(&[0,0])).err(), Some(String::from("Can't play there")));
+    assert_eq!(handle.cursor.pos, before);
+    assert!(handle.click(&game,&cells(&[0])).unwrap().is_some());
+
+    // the last move sends the next one to the middle board
+    let mut game: SuperTicTacToe<TicTacToe<P>,P> = SuperTicTacToe::new(P::X, |pos, player| TicTacToe::create(player, pos));
+    let m = handle.click(&game,&cells(&[0,4])).unwrap().expect("a cell in a board is a move");
+    game.mov(m);
+    assert!(handle.click(&game,&cells(&[0,0])).is_err());
+    assert!(handle.click(&game,&cells(&[4])).unwrap().is_none());
+    assert!(handle.click(&game,&cells(&[4,0])).unwrap().is_some());
+  }
+}
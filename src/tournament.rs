@@ -0,0 +1,275 @@
+use crate::tic_tac_toe::*;
+use crate::roster::RosterPlayer;
+use crate::options::Options;
+use crate::{Game,new_game,new_bots,time_budget};
+use std::io::{Write,stderr};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// A bot taking part, named by the text it was given so entrants with
+/// different settings can be told apart in the tables.
+#[derive(Clone, Debug)]
+pub struct Entrant {
+  pub name: String,
+  pub config: BotConfig,
+}
+
+impl Entrant {
+  pub fn parse<F: Fn(&str) -> Option<String>>(s: &str, find_team: F) -> Result<Entrant,String> {
+    Ok(Entrant{name: String::from(s), config: BotConfig::parse(s, find_team)?})
+  }
+}
+
+/// Wins, draws and losses.
+#[derive(Copy, Clone, Debug, Default)]
+struct Record {
+  won: u32,
+  drawn: u32,
+  lost: u32,
+}
+
+impl Record {
+  fn games(&self) -> u32 {
+    self.won+self.drawn+self.lost
+  }
+  fn score(&self) -> f64 {
+    self.won as f64+self.drawn as f64/2.0
+  }
+  fn add(&mut self, other: Record) {
+    self.won += other.won;
+    self.drawn += other.drawn;
+    self.lost += other.lost;
+  }
+}
+
+/// Plays one game headless, `seats[i]` being the bot of the player at
+/// position `i` in the roster.
+fn play(opts: &Options, seats: &[BotConfig], rng: &mut impl rand::Rng) -> Result<GameResult<RosterPlayer>,String> {
+  let roster = opts.roster;
+  let mut game: Game = new_game(&opts.game, roster, &opts.setup)?;
+  let mut bots = new_bots(seats, opts.threads, &opts.book);
+  let mut clock = opts.time.map(|control| GameClock::new(roster.players(), control));
+  loop {
+    match game.get_game_ref().get_status() {
+      BoardStatus::Won(player) => return Ok(GameResult::Won(player)),
+      BoardStatus::Draw => return Ok(GameResult::Draw),
+      BoardStatus::Playing => {},
+    }
+    let player = game.get_player();
+    let mut limit = None;
+    if let Some(clock) = &mut clock {
+      clock.start(player);
+      limit = Some(time_budget(clock.remaining(player), clock.get_control()));
+    }
+    let m = bots[player.get_id()].choose(&game, limit, rng);
+    if let Some(clock) = &mut clock {
+      clock.stop();
+      let result = clock.result();
+      if let GameResult::Incomplete = result {} else {
+        return Ok(result);
+      }
+    }
+    match m {
+      Some(m) => {
+        for bot in bots.iter_mut() {
+          bot.advance(&game, &m);
+        }
+        game.mov(std::rc::Rc::clone(&m));
+      },
+      None => return Ok(GameResult::Draw),
+    }
+  }
+}
+
+/// Ratings from everyone's results against everyone else, as the most
+/// likely Elo differences averaging 0, with 95% error bars. Each entrant
+/// also gets one made up draw against an average player, so a perfect
+/// score doesn't rate them at infinity.
+fn elo(records: &[Vec<Record>]) -> Vec<(f64,f64)> {
+  let n = records.len();
+  let scale = 400.0/10f64.ln();
+  let expected = |diff: f64| 1.0/(1.0+10f64.powf(-diff/400.0));
+  let mut ratings = vec![0.0; n];
+  let mut info = vec![0.0; n];
+  for _ in 0..200 {
+    for i in 0..n {
+      let mut score = 0.5;
+      let mut expect = expected(ratings[i]);
+      info[i] = expect*(1.0-expect);
+      for j in 0..n {
+        let games = records[i][j].games() as f64;
+        if i == j || games == 0.0 {
+          continue;
+        }
+        let p = expected(ratings[i]-ratings[j]);
+        score += records[i][j].score();
+        expect += games*p;
+        info[i] += games*p*(1.0-p);
+      }
+      ratings[i] += scale*(score-expect)/info[i];
+    }
+    let mean = ratings.iter().sum::<f64>()/(n as f64);
+    for r in ratings.iter_mut() {
+      *r -= mean;
+    }
+  }
+  ratings.iter().zip(info.iter()).map(|(&r,&i)| (r, 1.96*scale/i.sqrt())).collect()
+}
+
+/// The entrant in each roster seat for game `g` of `a` against `b`, and
+/// the turn `a` moves on, `ids` being the roster ids in turn order. `a`
+/// moves first in even games.
+fn seating(ids: &[usize], a: usize, b: usize, g: u32) -> ([usize; 2],usize) {
+  let order = if g%2 == 0 {[a,b]} else {[b,a]};
+  let mut seats = [0; 2];
+  seats[ids[0]] = order[0];
+  seats[ids[1]] = order[1];
+  (seats, (g%2) as usize)
+}
+
+/// Plays every pairing of entrants `opts.games` times, or in a gauntlet
+/// only the first entrant against each of the others, with the entrants
+/// swapping seats every game. Prints the tables to stdout and progress to
+/// stderr.
+pub fn run(opts: &Options) -> Result<(),String> {
+  let entrants = &opts.entrants;
+  if opts.roster.len() != 2 {
+    return Err(format!("tournaments are played by 2 players, not {}", opts.roster.len()));
+  }
+  if entrants.len() < 2 {
+    return Err(String::from("a tournament needs at least 2 entrants"));
+  }
+  let n = entrants.len();
+  let mut pairings = vec!();
+  for a in 0..n {
+    for b in a+1..n {
+      if !opts.gauntlet || a == 0 {
+        pairings.push((a,b));
+      }
+    }
+  }
+  let ids: Vec<usize> = opts.roster.players().iter().map(|p| p.get_id()).collect();
+  let mut records = vec![vec![Record::default(); n]; n];
+  let seed = opts.seed.unwrap_or_else(rand::random);
+  eprintln!("seed {}", seed);
+  let mut rng = StdRng::seed_from_u64(seed);
+  for &(a,b) in pairings.iter() {
+    let mut record = Record::default();
+    for g in 0..opts.games {
+      let (seated,turn) = seating(&ids, a, b, g);
+      let seats: Vec<BotConfig> = seated.iter().map(|&e| entrants[e].config.clone()).collect();
+      let a_player = opts.roster.players()[turn];
+      match play(opts, &seats, &mut rng)? {
+        GameResult::Won(winner) if winner.is_teammate(&a_player) => record.won += 1,
+        GameResult::Won(_) => record.lost += 1,
+        _ => record.drawn += 1,
+      }
+      eprint!("\r{} vs {}: +{} ={} -{}", entrants[a].name, entrants[b].name, record.won, record.drawn, record.lost);
+      stderr().flush().ok();
+    }
+    eprintln!();
+    records[a][b].add(record);
+    records[b][a].add(Record{won: record.lost, drawn: record.drawn, lost: record.won});
+  }
+  print_tables(entrants, &records);
+  Ok(())
+}
+
+fn print_tables(entrants: &[Entrant], records: &[Vec<Record>]) {
+  let width = entrants.iter().map(|e| e.name.len()).max().unwrap_or(0).max(7);
+  print!("{:w$}", "", w = width);
+  for e in entrants.iter() {
+    print!("  {:>w$}", e.name, w = width);
+  }
+  println!();
+  for (i,e) in entrants.iter().enumerate() {
+    print!("{:w$}", e.name, w = width);
+    for (j,r) in records[i].iter().enumerate() {
+      let cell = if i == j || r.games() == 0 {
+        String::from("-")
+      } else {
+        format!("{}-{}-{}", r.won, r.drawn, r.lost)
+      };
+      print!("  {:>w$}", cell, w = width);
+    }
+    println!();
+  }
+  println!();
+  let ratings = elo(records);
+  let mut rows: Vec<usize> = (0..entrants.len()).collect();
+  rows.sort_by(|&a,&b| ratings[b].0.partial_cmp(&ratings[a].0).unwrap());
+  println!("{:w$}  {:>5}  {:>4}  {:>4}  {:>4}  {:>6}  {:>5}", "entrant", "games", "W", "D", "L", "score", "elo", w = width);
+  for i in rows.into_iter() {
+    let mut total = Record::default();
+    for r in records[i].iter() {
+      total.add(*r);
+    }
+    let (rating,error) = ratings[i];
+    println!("{:w$}  {:>5}  {:>4}  {:>4}  {:>4}  {:>5.1}%  {:>+5.0} ±{:<4.0}",
+      entrants[i].name, total.games(), total.won, total.drawn, total.lost,
+      100.0*total.score()/(total.games().max(1) as f64), rating, error, w = width);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn record(won: u32, drawn: u32, lost: u32) -> Record {
+    Record{won, drawn, lost}
+  }
+
+  /// Records of two entrants from the first one's `r`.
+  fn pair(r: Record) -> Vec<Vec<Record>> {
+    vec![vec![Record::default(), r], vec![record(r.lost, r.drawn, r.won), Record::default()]]
+  }
+
+  #[test]
+  fn even_records_rate_even() {
+    let ratings = elo(&pair(record(5, 10, 5)));
+    assert!(ratings[0].0.abs() < 1e-6 && ratings[1].0.abs() < 1e-6);
+    assert!((ratings[0].1-ratings[1].1).abs() < 1e-6);
+  }
+
+  #[test]
+  fn three_to_one() {
+    // 30 out of 40 is 400*log10(3) = 191 apart, less a little for the
+    // made up draws: 30.5 = E(r)+40*E(2r) puts them at 93.9 either side
+    let ratings = elo(&pair(record(30, 0, 10)));
+    assert!((ratings[0].0-93.9).abs() < 0.5, "{:?}", ratings);
+    assert!((ratings[0].0+ratings[1].0).abs() < 1e-6);
+    // four times the games, half the error bar
+    let more = elo(&pair(record(120, 0, 40)));
+    assert!((more[0].0-ratings[0].0).abs() < 2.0);
+    assert!((ratings[0].1/more[0].1-2.0).abs() < 0.05, "{:?} {:?}", ratings, more);
+  }
+
+  #[test]
+  fn perfect_scores_stay_finite() {
+    let ratings = elo(&pair(record(20, 0, 0)));
+    assert!(ratings[0].0.is_finite() && ratings[0].0 > 300.0);
+    assert!(ratings[0].1.is_finite());
+  }
+
+  #[test]
+  fn ratings_follow_results() {
+    let mut records = vec![vec![Record::default(); 3]; 3];
+    for &(i,j,r) in [(0,1,record(12, 4, 4)), (1,2,record(12, 4, 4)), (0,2,record(16, 2, 2))].iter() {
+      records[i][j] = r;
+      records[j][i] = record(r.lost, r.drawn, r.won);
+    }
+    let ratings = elo(&records);
+    assert!(ratings[0].0 > ratings[1].0 && ratings[1].0 > ratings[2].0);
+    assert!(ratings.iter().map(|r| r.0).sum::<f64>().abs() < 1e-6);
+  }
+
+  #[test]
+  fn entrants_swap_seats() {
+    // X moves first
+    assert_eq!(seating(&[0, 1], 3, 5, 0), ([3, 5], 0));
+    assert_eq!(seating(&[0, 1], 3, 5, 1), ([5, 3], 1));
+    // with --order O,X, O in seat 1 moves first
+    assert_eq!(seating(&[1, 0], 3, 5, 0), ([5, 3], 0));
+    assert_eq!(seating(&[1, 0], 3, 5, 1), ([3, 5], 1));
+  }
+}